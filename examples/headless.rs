@@ -0,0 +1,79 @@
+//! Renders the triangle and sprite scenes without a window and saves them as PNGs.
+//!
+//! `cargo run --example headless --features headless,image`
+use gl_tests_god_save_me::*;
+
+const TRIANGLE_DATA: [f32; 15] = [
+    0.0, 0.5, // first pos
+    1.0, 0.819, 0.729, // first color
+    0.5, -0.5, // second pos
+    0.807, 0.490, 0.647, // second color
+    -0.5, -0.5, // third pos
+    0.745, 0.8980, 0.749, // third color
+];
+
+const VERTEX_SOURCE: &str = r#"
+#version 330 core
+layout (location = 0) in vec2 position;
+layout (location = 1) in vec3 color;
+out vec3 fragment_color;
+
+void main() {
+    gl_Position = vec4(position, 1.0, 1.0);
+    fragment_color = color;
+}
+"#;
+
+const FRAGMENT_SOURCE: &str = r#"
+#version 330 core
+in vec3 fragment_color;
+out vec4 color;
+
+void main() {
+    color = vec4(fragment_color, 1.0);
+}
+"#;
+
+fn main() -> Result<(), AnyError> {
+    let _context = HeadlessContext::new(640, 480)?;
+
+    set_clear_color(0.3, 0.8, 1.0, 1.0);
+    clear(ClearFlags::COLOR);
+
+    let vao = Vao::new();
+    vao.bind();
+    let buffer = Buffer::new(DrawTarget::Array);
+    buffer.bind();
+    buffer.data(&TRIANGLE_DATA, DrawUsage::StaticDraw);
+
+    let program = Program::new(Shader::new(VERTEX_SOURCE)?, Shader::new(FRAGMENT_SOURCE)?)?;
+    program.use_internal();
+    setup_attribute(0, 2, 0, 5, AttributeType::f32);
+    setup_attribute(1, 3, 2, 5, AttributeType::f32);
+
+    vao.draw_arrays(buffer::DrawMode::Triangles, 0, 3);
+    capture_framebuffer().save("triangle.png")?;
+
+    set_clear_color(
+        0x37 as f32 / 255.0,
+        0x69 as f32 / 255.0,
+        0x96 as f32 / 255.0,
+        1.0,
+    );
+    clear(ClearFlags::COLOR);
+
+    let camera = Camera::new((640, 480));
+    let texture = Texture::from_path(
+        "assets/livekohazereaction.png",
+        &ImageLoadOptions::default(),
+    )?;
+    let size = (texture.width() as u32, texture.height() as u32);
+    let mut active_texture = ActiveTexture::new(0)?;
+    active_texture.bind_texture(&texture);
+    let sprite = Sprite::new(active_texture, size)?;
+
+    sprite.render((100.0, 100.0), camera.calculate_projection_ortho(), 1.0);
+    capture_framebuffer().save("sprite.png")?;
+
+    Ok(())
+}
@@ -148,7 +148,7 @@ fn main() -> Result<(), AnyError> {
         image.height() as i32,
     );
 
-    let mut active_texture = ActiveTexture::new(0);
+    let mut active_texture = ActiveTexture::new(0)?;
     active_texture.bind_texture(&texture);
     program.put_uniform("pyramid_texture", &active_texture)?;
 
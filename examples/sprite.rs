@@ -46,7 +46,7 @@ fn main() -> Result<(), AnyError> {
         image.height() as i32,
     );
 
-    let mut active_texture = ActiveTexture::new(0);
+    let mut active_texture = ActiveTexture::new(0)?;
     active_texture.bind_texture(&texture);
     let sprite = Sprite::new(active_texture, (image.width(), image.height()))?;
     let mut scale = 1.0;
@@ -109,7 +109,7 @@ fn main() -> Result<(), AnyError> {
         image.height() as i32,
     );
 
-    let mut active_texture = ActiveTexture::new(0);
+    let mut active_texture = ActiveTexture::new(0)?;
     active_texture.bind_texture(&texture);
 
     let texture_atlas = SpriteSheet::new(active_texture, (78.0, 70.0), (6.0, 10.0))?;
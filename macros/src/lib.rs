@@ -0,0 +1,99 @@
+mod uniforms;
+mod validate;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    DeriveInput, Ident, LitStr, Token,
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+};
+
+struct GlslInput {
+    stage: Ident,
+    source: LitStr,
+}
+
+impl Parse for GlslInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let stage = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let source = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+
+        Ok(Self { stage, source })
+    }
+}
+
+/// Validate GLSL at compile time and turn it into a `ShaderSource` for the given stage.
+///
+/// ```ignore
+/// const VERTEX: ShaderSource<Vertex> = glsl!(vertex, r#"
+/// #version 330 core
+/// void main() { gl_Position = vec4(0.0); }
+/// "#);
+/// ```
+///
+/// Supported stages are `vertex` and `fragment`.
+#[proc_macro]
+pub fn glsl(input: TokenStream) -> TokenStream {
+    let GlslInput { stage, source } = parse_macro_input!(input as GlslInput);
+
+    let (naga_stage, stage_type) = match stage.to_string().as_str() {
+        "vertex" => (naga::ShaderStage::Vertex, quote!(Vertex)),
+        "fragment" => (naga::ShaderStage::Fragment, quote!(Fragment)),
+        other => {
+            return syn::Error::new(
+                stage.span(),
+                format!("unknown shader stage `{other}`, expected `vertex` or `fragment`"),
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let code = source.value();
+    if let Err(diagnostics) = validate::validate(&code, naga_stage) {
+        // proc macros can't point inside a string literal on stable, so the
+        // location is part of the message instead
+        let errors = diagnostics.into_iter().map(|d| {
+            let line = code.lines().nth(d.line - 1).unwrap_or_default();
+            let message = format!(
+                "invalid GLSL at line {}, column {}: {}\n{} | {}\n{} | {}^",
+                d.line,
+                d.column,
+                d.message,
+                d.line,
+                line,
+                " ".repeat(d.line.to_string().len()),
+                " ".repeat(d.column - 1),
+            );
+            syn::Error::new(source.span(), message).to_compile_error()
+        });
+
+        let errors: Vec<_> = errors.collect();
+        return quote!({
+            #(#errors)*
+            ::gl_tests_god_save_me::ShaderSource::<::gl_tests_god_save_me::#stage_type>::new_unchecked(#source)
+        })
+        .into();
+    }
+
+    quote!(
+        ::gl_tests_god_save_me::ShaderSource::<::gl_tests_god_save_me::#stage_type>::new_unchecked(#source)
+    )
+    .into()
+}
+
+/// Implement `Uniforms` for a struct with named fields, mapping every field to the
+/// uniform of the same name.
+///
+/// Fields can be renamed with `#[uniform(rename = "name")]` and left out with
+/// `#[uniform(skip)]`. Every other field has to implement `Uniform`.
+#[proc_macro_derive(Uniforms, attributes(uniform))]
+pub fn derive_uniforms(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    uniforms::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,87 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, spanned::Spanned};
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "Uniforms can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            data.fields.span(),
+            "Uniforms can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut names = vec![];
+    let mut accepts = vec![];
+    let mut puts = vec![];
+
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut name = ident.to_string();
+        let mut skip = false;
+
+        for attribute in field.attrs.iter().filter(|a| a.path().is_ident("uniform")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"...\"` or `skip`"))
+                }
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        let index = names.len();
+        let ty = &field.ty;
+        accepts.push(quote!(
+            #index => <#ty as ::gl_tests_god_save_me::Uniform>::accepts_gl_type(gl_type)
+        ));
+        puts.push(quote!(
+            ::gl_tests_god_save_me::Uniform::put_uniform(&self.#ident, locations[#index])?
+        ));
+        names.push(name);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::gl_tests_god_save_me::Uniforms for #ident #type_generics #where_clause {
+            const NAMES: &'static [&'static str] = &[#(#names),*];
+
+            fn layout_key() -> usize {
+                static KEY: u8 = 0;
+                &KEY as *const u8 as usize
+            }
+
+            fn accepts_gl_type(index: usize, gl_type: u32) -> bool {
+                match index {
+                    #(#accepts,)*
+                    _ => false,
+                }
+            }
+
+            unsafe fn put_uniforms(
+                &self,
+                locations: &[i32],
+            ) -> ::core::result::Result<(), ::gl_tests_god_save_me::ShaderError> {
+                unsafe {
+                    #(#puts;)*
+                }
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
@@ -0,0 +1,297 @@
+//! Validation of OpenGL flavoured GLSL with the naga GLSL front end.
+//!
+//! naga only understands Vulkan flavoured GLSL 4.40+, so the source is rewritten before
+//! parsing: the `#version` is bumped, loose uniforms are moved into blocks with explicit
+//! bindings and combined samplers are split into a texture and a sampler. The rewrite
+//! keeps every line where it was so errors can be reported against the original source.
+
+use naga::{
+    ShaderStage,
+    front::glsl::{Frontend, Options},
+    valid::{Capabilities, ValidationFlags, Validator},
+};
+use std::{error::Error as _, ops};
+
+pub struct Diagnostic {
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in bytes
+    pub column: usize,
+    pub message: String,
+}
+
+pub fn validate(source: &str, stage: ShaderStage) -> Result<(), Vec<Diagnostic>> {
+    let rewritten = Rewritten::new(source)?;
+
+    let module = Frontend::default()
+        .parse(&Options::from(stage), &rewritten.source)
+        .map_err(|errors| {
+            errors
+                .errors
+                .iter()
+                .map(|e| {
+                    rewritten.diagnostic(e.meta.location(&rewritten.source), e.kind.to_string())
+                })
+                .collect::<Vec<_>>()
+        })?;
+
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| {
+            let mut message = e.as_inner().to_string();
+            let mut source = e.as_inner().source();
+            while let Some(inner) = source {
+                message.push_str(": ");
+                message.push_str(&inner.to_string());
+                source = inner.source();
+            }
+
+            // the innermost span is the most precise one
+            let location = e
+                .spans()
+                .filter(|(span, _)| span.is_defined())
+                .last()
+                .map(|(span, _)| span.location(&rewritten.source));
+
+            match location {
+                Some(location) => vec![rewritten.diagnostic(location, message)],
+                None => vec![Diagnostic {
+                    line: 1,
+                    column: 1,
+                    message,
+                }],
+            }
+        })?;
+
+    Ok(())
+}
+
+/// A replaced part of a line: the range in the rewritten line and the range it replaced.
+type Replacement = (ops::Range<usize>, ops::Range<usize>);
+
+struct Rewritten {
+    source: String,
+    /// Per line, the parts that were replaced. `None` if the whole line was rewritten.
+    replacements: Vec<Option<Vec<Replacement>>>,
+    original_indents: Vec<usize>,
+}
+
+impl Rewritten {
+    fn new(source: &str) -> Result<Self, Vec<Diagnostic>> {
+        let mut lines: Vec<String> = vec![];
+        let mut replacements = vec![];
+        let mut samplers: Vec<(String, &'static str)> = vec![];
+        let mut version_found = false;
+        let mut binding = 0;
+
+        for line in source.lines() {
+            let trimmed = line.trim_start();
+
+            if let Some(directive) = trimmed.strip_prefix('#')
+                && directive.trim_start().starts_with("version")
+            {
+                version_found = true;
+                lines.push("#version 450 core".to_owned());
+                replacements.push(None);
+                continue;
+            }
+
+            if let Some(declaration) = uniform_declaration(trimmed) {
+                let (ty, names) = declaration
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((declaration, ""));
+
+                let mut rewritten = String::new();
+                if let Some((texture, sampler, constructor)) = combined_sampler(ty) {
+                    for name in names.split(',').map(str::trim) {
+                        rewritten.push_str(&format!(
+                            "layout(set = 0, binding = {binding}) uniform {texture} {name}; \
+                             layout(set = 0, binding = {}) uniform {sampler} {name}_sampler; ",
+                            binding + 1
+                        ));
+                        binding += 2;
+                        samplers.push((name.to_owned(), constructor));
+                    }
+                } else {
+                    rewritten = format!(
+                        "layout(binding = {binding}) uniform Uniforms{binding} {{ {declaration}; }};"
+                    );
+                    binding += 1;
+                }
+
+                lines.push(rewritten);
+                replacements.push(None);
+                continue;
+            }
+
+            lines.push(line.to_owned());
+            replacements.push(Some(vec![]));
+        }
+
+        if !version_found {
+            return Err(vec![Diagnostic {
+                line: 1,
+                column: 1,
+                message: "missing #version directive".to_owned(),
+            }]);
+        }
+
+        // samplers can only be used through a constructor in Vulkan GLSL
+        for (line, replaced) in lines.iter_mut().zip(replacements.iter_mut()) {
+            if let Some(replaced) = replaced {
+                *line = replace_sampler_uses(line, &samplers, replaced);
+            }
+        }
+
+        Ok(Self {
+            source: lines.join("\n"),
+            replacements,
+            original_indents: source
+                .lines()
+                .map(|l| l.len() - l.trim_start().len())
+                .collect(),
+        })
+    }
+
+    fn diagnostic(&self, location: naga::SourceLocation, message: String) -> Diagnostic {
+        let line = location.line_number as usize;
+        let column = location.line_position as usize - 1;
+
+        let column = match self.replacements.get(line - 1) {
+            Some(Some(replaced)) => {
+                let mut shift = 0;
+                let mut mapped = None;
+                for (rewritten, original) in replaced {
+                    if column < rewritten.start {
+                        break;
+                    }
+                    if column < rewritten.end {
+                        mapped = Some(original.start);
+                        break;
+                    }
+                    shift += rewritten.len() - original.len();
+                }
+                mapped.unwrap_or(column - shift)
+            }
+            _ => self.original_indents.get(line - 1).copied().unwrap_or(0),
+        };
+
+        Diagnostic {
+            line,
+            column: column + 1,
+            message,
+        }
+    }
+}
+
+/// Returns the `type names` part of `[layout(...)] uniform type names;`,
+/// `None` for uniform blocks and everything else.
+fn uniform_declaration(line: &str) -> Option<&str> {
+    let line = match line.strip_prefix("layout") {
+        Some(rest) => rest
+            .trim_start()
+            .strip_prefix('(')?
+            .split_once(')')?
+            .1
+            .trim_start(),
+        None => line,
+    };
+
+    let declaration = line.strip_prefix("uniform")?;
+    if !declaration.starts_with(char::is_whitespace) || declaration.contains('{') {
+        return None;
+    }
+
+    let declaration = declaration
+        .split("//")
+        .next()?
+        .trim()
+        .strip_suffix(';')?
+        .trim();
+
+    (!declaration.is_empty()).then_some(declaration)
+}
+
+/// Map a combined sampler type to the texture type, the sampler type and the constructor.
+fn combined_sampler(ty: &str) -> Option<(String, &'static str, &'static str)> {
+    const TYPES: [&str; 27] = [
+        "sampler1D",
+        "sampler2D",
+        "sampler3D",
+        "samplerCube",
+        "sampler1DArray",
+        "sampler2DArray",
+        "samplerCubeArray",
+        "sampler2DMS",
+        "sampler2DMSArray",
+        "isampler1D",
+        "isampler2D",
+        "isampler3D",
+        "isamplerCube",
+        "isampler1DArray",
+        "isampler2DArray",
+        "isampler2DMS",
+        "usampler1D",
+        "usampler2D",
+        "usampler3D",
+        "usamplerCube",
+        "usampler1DArray",
+        "usampler2DArray",
+        "usampler2DMS",
+        "sampler1DShadow",
+        "sampler2DShadow",
+        "samplerCubeShadow",
+        "sampler2DArrayShadow",
+    ];
+
+    let constructor = TYPES.into_iter().find(|t| *t == ty)?;
+    let (texture, sampler) = match constructor.strip_suffix("Shadow") {
+        Some(base) => (base.replacen("sampler", "texture", 1), "samplerShadow"),
+        None => (constructor.replacen("sampler", "texture", 1), "sampler"),
+    };
+
+    Some((texture, sampler, constructor))
+}
+
+fn replace_sampler_uses(
+    line: &str,
+    samplers: &[(String, &'static str)],
+    replaced: &mut Vec<Replacement>,
+) -> String {
+    if samplers.is_empty() {
+        return line.to_owned();
+    }
+
+    let code = line.split("//").next().unwrap_or(line);
+    let mut output = String::with_capacity(line.len());
+    let mut chars = code.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if !(c.is_ascii_alphabetic() || c == '_') {
+            output.push(c);
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) = chars.peek().copied() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+
+        let identifier = &code[start..end];
+        match samplers.iter().find(|(name, _)| name == identifier) {
+            Some((name, constructor)) => {
+                let replacement = format!("{constructor}({name}, {name}_sampler)");
+                replaced.push((output.len()..output.len() + replacement.len(), start..end));
+                output.push_str(&replacement);
+            }
+            None => output.push_str(identifier),
+        }
+    }
+
+    output.push_str(&line[code.len()..]);
+    output
+}
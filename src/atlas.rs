@@ -0,0 +1,536 @@
+use crate::{Texture, TextureError, TextureFormat};
+use core::{error, fmt};
+use std::collections::HashMap;
+
+/// Where an image ended up in a [`TextureAtlas`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    /// The index of the page texture.
+    pub page: usize,
+    /// The position of the image on the page in pixels, without the extruded border.
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The texture coordinates of the `(x, y)` corner.
+    pub uv_min: [f32; 2],
+    /// The texture coordinates of the `(x + width, y + height)` corner.
+    pub uv_max: [f32; 2],
+}
+
+#[derive(Debug)]
+pub enum AtlasError {
+    DataSizeMismatch {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// The image doesn't fit on a page, even with nothing else on it.
+    ImageTooLarge {
+        name: String,
+        width: u32,
+        height: u32,
+    },
+    DuplicateName(String),
+    EmptyImage(String),
+    Texture(TextureError),
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DataSizeMismatch {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Image {name} has {actual} bytes, but its size needs {expected}"
+            ),
+            Self::ImageTooLarge {
+                name,
+                width,
+                height,
+            } => write!(f, "Image {name} ({width}x{height}) is larger than a page"),
+            Self::DuplicateName(name) => write!(f, "Image {name} was added twice"),
+            Self::EmptyImage(name) => write!(f, "Image {name} has no pixels"),
+            Self::Texture(e) => write!(f, "Cannot create the page texture: {e}"),
+        }
+    }
+}
+
+impl error::Error for AtlasError {}
+
+impl From<TextureError> for AtlasError {
+    fn from(value: TextureError) -> Self {
+        Self::Texture(value)
+    }
+}
+
+struct PendingImage {
+    name: String,
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// Packs RGBA8 images of any size into as few page textures as possible.
+///
+/// ```ignore
+/// let mut builder = TextureAtlasBuilder::new(1024, 1024).padding(2).extrusion(1);
+/// builder.add("player", &player_pixels, 32, 48)?;
+/// builder.add("coin", &coin_pixels, 16, 16)?;
+/// let atlas = builder.build()?;
+///
+/// let region = atlas.region("coin").unwrap();
+/// active_texture.bind_texture(atlas.page(region.page));
+/// let sprite = Sprite::from_region(active_texture, region)?;
+/// ```
+pub struct TextureAtlasBuilder {
+    page_width: u32,
+    page_height: u32,
+    padding: u32,
+    extrusion: u32,
+    images: Vec<PendingImage>,
+}
+
+impl TextureAtlasBuilder {
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        Self {
+            page_width,
+            page_height,
+            padding: 0,
+            extrusion: 0,
+            images: vec![],
+        }
+    }
+
+    /// Empty pixels between the images.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Repeat the edge pixels of every image this many times around it, so
+    /// filtering at the edges doesn't pick up the neighbours.
+    pub fn extrusion(mut self, extrusion: u32) -> Self {
+        self.extrusion = extrusion;
+        self
+    }
+
+    /// Add tightly packed RGBA8 pixels under `name`.
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<(), AtlasError> {
+        let name = name.into();
+        if self.images.iter().any(|i| i.name == name) {
+            return Err(AtlasError::DuplicateName(name));
+        }
+        if width == 0 || height == 0 {
+            return Err(AtlasError::EmptyImage(name));
+        }
+
+        let expected = width as usize * height as usize * 4;
+        if data.len() != expected {
+            return Err(AtlasError::DataSizeMismatch {
+                name,
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        self.images.push(PendingImage {
+            name,
+            data: data.to_vec(),
+            width,
+            height,
+        });
+        Ok(())
+    }
+
+    /// Pack the images and upload every page as a [`Texture`].
+    pub fn build(self) -> Result<TextureAtlas, AtlasError> {
+        let (width, height) = (self.page_width as i32, self.page_height as i32);
+        let packed = self.pack()?;
+
+        let pages = packed
+            .pages
+            .iter()
+            .map(|pixels| Texture::with_format(Some(pixels), width, height, TextureFormat::Rgba8))
+            .collect::<Result<_, _>>()?;
+
+        Ok(TextureAtlas {
+            pages,
+            regions: packed.regions,
+        })
+    }
+
+    /// Pack the images without touching GL.
+    pub fn pack(mut self) -> Result<PackedAtlas, AtlasError> {
+        let border = 2 * self.extrusion + self.padding;
+        for image in &self.images {
+            if image.width + border > self.page_width || image.height + border > self.page_height {
+                return Err(AtlasError::ImageTooLarge {
+                    name: image.name.clone(),
+                    width: image.width,
+                    height: image.height,
+                });
+            }
+        }
+
+        // big images first leave the gaps for the small ones
+        self.images
+            .sort_by_key(|i| std::cmp::Reverse(i.width.max(i.height)));
+
+        let mut bins: Vec<MaxRects> = vec![];
+        let mut pages: Vec<Vec<u8>> = vec![];
+        let mut regions = HashMap::with_capacity(self.images.len());
+
+        for image in &self.images {
+            let (cell_width, cell_height) = (image.width + border, image.height + border);
+            let placed = bins
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, bin)| Some((page, bin.insert(cell_width, cell_height)?)));
+
+            let (page, (x, y)) = match placed {
+                Some(placed) => placed,
+                None => {
+                    let mut bin = MaxRects::new(self.page_width, self.page_height);
+                    let position = bin
+                        .insert(cell_width, cell_height)
+                        .expect("The image should fit on an empty page");
+                    bins.push(bin);
+                    pages.push(vec![
+                        0;
+                        self.page_width as usize * self.page_height as usize * 4
+                    ]);
+                    (bins.len() - 1, position)
+                }
+            };
+
+            let (x, y) = (x + self.extrusion, y + self.extrusion);
+            self.blit(&mut pages[page], image, x, y);
+
+            let (page_width, page_height) = (self.page_width as f32, self.page_height as f32);
+            regions.insert(
+                image.name.clone(),
+                AtlasRegion {
+                    page,
+                    x,
+                    y,
+                    width: image.width,
+                    height: image.height,
+                    uv_min: [x as f32 / page_width, y as f32 / page_height],
+                    uv_max: [
+                        (x + image.width) as f32 / page_width,
+                        (y + image.height) as f32 / page_height,
+                    ],
+                },
+            );
+        }
+
+        Ok(PackedAtlas {
+            page_width: self.page_width,
+            page_height: self.page_height,
+            pages,
+            regions,
+        })
+    }
+
+    /// Copy the image to `x`, `y`, with its edges extruded.
+    fn blit(&self, page: &mut [u8], image: &PendingImage, x: u32, y: u32) {
+        let extrusion = self.extrusion as i64;
+        let page_width = self.page_width as usize;
+
+        for row in -extrusion..image.height as i64 + extrusion {
+            let source_row = row.clamp(0, image.height as i64 - 1) as usize;
+            for column in -extrusion..image.width as i64 + extrusion {
+                let source_column = column.clamp(0, image.width as i64 - 1) as usize;
+                let source = (source_row * image.width as usize + source_column) * 4;
+                let target =
+                    ((y as i64 + row) as usize * page_width + (x as i64 + column) as usize) * 4;
+                page[target..target + 4].copy_from_slice(&image.data[source..source + 4]);
+            }
+        }
+    }
+}
+
+/// The result of [`TextureAtlasBuilder::pack`], RGBA8 pages in memory.
+#[derive(Debug, Clone)]
+pub struct PackedAtlas {
+    pub page_width: u32,
+    pub page_height: u32,
+    pub pages: Vec<Vec<u8>>,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+/// Page textures with the named regions packed into them.
+#[derive(Debug)]
+pub struct TextureAtlas {
+    pages: Vec<Texture>,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    /// # Panics
+    /// If there's no page `index`.
+    pub fn page(&self, index: usize) -> &Texture {
+        &self.pages[index]
+    }
+
+    pub fn pages(&self) -> &[Texture] {
+        &self.pages
+    }
+
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (&str, &AtlasRegion)> {
+        self.regions
+            .iter()
+            .map(|(name, region)| (name.as_str(), region))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        other.x < self.x + self.width
+            && other.x + other.width > self.x
+            && other.y < self.y + self.height
+            && other.y + other.height > self.y
+    }
+}
+
+/// The maximal rectangles packer, placing by best short side fit.
+struct MaxRects {
+    free: Vec<Rect>,
+}
+
+impl MaxRects {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            free: vec![Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }],
+        }
+    }
+
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let best = self
+            .free
+            .iter()
+            .filter(|r| r.width >= width && r.height >= height)
+            .min_by_key(|r| {
+                let (dx, dy) = (r.width - width, r.height - height);
+                (dx.min(dy), dx.max(dy))
+            })?;
+
+        let placed = Rect {
+            x: best.x,
+            y: best.y,
+            width,
+            height,
+        };
+
+        let mut split = Vec::new();
+        self.free.retain(|free| {
+            if !free.intersects(&placed) {
+                return true;
+            }
+
+            // keep the maximal rectangles around the placed one
+            if placed.x > free.x {
+                split.push(Rect {
+                    width: placed.x - free.x,
+                    ..*free
+                });
+            }
+            if placed.x + placed.width < free.x + free.width {
+                split.push(Rect {
+                    x: placed.x + placed.width,
+                    width: free.x + free.width - placed.x - placed.width,
+                    ..*free
+                });
+            }
+            if placed.y > free.y {
+                split.push(Rect {
+                    height: placed.y - free.y,
+                    ..*free
+                });
+            }
+            if placed.y + placed.height < free.y + free.height {
+                split.push(Rect {
+                    y: placed.y + placed.height,
+                    height: free.y + free.height - placed.y - placed.height,
+                    ..*free
+                });
+            }
+            false
+        });
+        self.free.extend(split);
+
+        // drop rectangles inside of others
+        let mut index = 0;
+        while index < self.free.len() {
+            let rect = self.free[index];
+            let contained = self.free.iter().enumerate().any(|(other, r)| {
+                other != index && r.contains(&rect) && (!rect.contains(r) || other < index)
+            });
+            if contained {
+                self.free.swap_remove(index);
+            } else {
+                index += 1;
+            }
+        }
+
+        Some((placed.x, placed.y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, value: u8) -> Vec<u8> {
+        vec![value; width as usize * height as usize * 4]
+    }
+
+    fn pack(builder: TextureAtlasBuilder, sizes: &[(u32, u32)]) -> Result<PackedAtlas, AtlasError> {
+        let mut builder = builder;
+        for (index, &(width, height)) in sizes.iter().enumerate() {
+            builder.add(index.to_string(), &solid(width, height, 255), width, height)?;
+        }
+        builder.pack()
+    }
+
+    #[test]
+    fn exact_fit() {
+        let packed = pack(TextureAtlasBuilder::new(8, 8), &[(4, 4); 4]).unwrap();
+        assert_eq!(packed.pages.len(), 1);
+        assert!(packed.pages[0].iter().all(|byte| *byte == 255));
+
+        let mut corners: Vec<_> = packed.regions.values().map(|r| (r.x, r.y)).collect();
+        corners.sort();
+        assert_eq!(corners, [(0, 0), (0, 4), (4, 0), (4, 4)]);
+    }
+
+    #[test]
+    fn padding() {
+        let builder = TextureAtlasBuilder::new(12, 6).padding(2);
+        let packed = pack(builder, &[(4, 4), (4, 4)]).unwrap();
+        assert_eq!(packed.pages.len(), 1);
+
+        let mut regions: Vec<_> = packed.regions.values().collect();
+        regions.sort_by_key(|r| r.x);
+        assert_eq!((regions[0].x, regions[1].x), (0, 6));
+        // the padding stays empty
+        let page = &packed.pages[0];
+        assert!((4..6).all(|x| page[x * 4..x * 4 + 4] == [0; 4]));
+    }
+
+    #[test]
+    fn extrusion() {
+        let mut builder = TextureAtlasBuilder::new(4, 4).extrusion(1);
+        let pixels = [[10; 4], [20; 4], [30; 4], [40; 4]].concat();
+        builder.add("image", &pixels, 2, 2).unwrap();
+        let packed = builder.pack().unwrap();
+
+        let region = packed.regions["image"];
+        assert_eq!((region.x, region.y), (1, 1));
+        assert_eq!(region.uv_min, [0.25, 0.25]);
+        assert_eq!(region.uv_max, [0.75, 0.75]);
+        // every corner of the border repeats the nearest pixel
+        let page = &packed.pages[0];
+        let red = |x: usize, y: usize| page[(y * 4 + x) * 4];
+        assert_eq!(
+            [red(0, 0), red(3, 0), red(0, 3), red(3, 3)],
+            [10, 20, 30, 40]
+        );
+    }
+
+    #[test]
+    fn overflow_onto_a_new_page() {
+        let packed = pack(TextureAtlasBuilder::new(4, 4), &[(4, 4), (4, 4), (2, 2)]).unwrap();
+        assert_eq!(packed.pages.len(), 3);
+
+        let mut pages: Vec<_> = packed.regions.values().map(|r| r.page).collect();
+        pages.sort();
+        assert_eq!(pages, [0, 1, 2]);
+    }
+
+    #[test]
+    fn image_larger_than_the_page() {
+        let error = pack(TextureAtlasBuilder::new(4, 4), &[(2, 2), (5, 1)]).unwrap_err();
+        assert!(matches!(
+            error,
+            AtlasError::ImageTooLarge {
+                width: 5,
+                height: 1,
+                ..
+            }
+        ));
+
+        // fits on its own, but not with the padding around it
+        let error = pack(TextureAtlasBuilder::new(4, 4).padding(1), &[(4, 4)]).unwrap_err();
+        assert!(matches!(error, AtlasError::ImageTooLarge { .. }));
+    }
+
+    #[test]
+    fn no_overlapping_regions() {
+        let sizes: Vec<_> = (0..40).map(|i| (1 + i * 7 % 13, 1 + i * 5 % 11)).collect();
+        let (padding, extrusion) = (1, 1);
+        let builder = TextureAtlasBuilder::new(32, 32)
+            .padding(padding)
+            .extrusion(extrusion);
+        let packed = pack(builder, &sizes).unwrap();
+        assert_eq!(packed.regions.len(), sizes.len());
+
+        // the images with their extruded borders
+        let cells: Vec<_> = packed
+            .regions
+            .values()
+            .map(|r| {
+                let cell = Rect {
+                    x: r.x - extrusion,
+                    y: r.y - extrusion,
+                    width: r.width + 2 * extrusion,
+                    height: r.height + 2 * extrusion,
+                };
+                (r.page, cell)
+            })
+            .collect();
+        for (index, (page, cell)) in cells.iter().enumerate() {
+            assert!(cell.x + cell.width <= 32 && cell.y + cell.height <= 32);
+            for (other_page, other) in &cells[index + 1..] {
+                assert!(
+                    page != other_page || !cell.intersects(other),
+                    "{cell:?} overlaps {other:?}"
+                );
+            }
+        }
+    }
+}
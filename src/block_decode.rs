@@ -0,0 +1,674 @@
+//! CPU decoders for the block compressed formats, used when the driver can't sample them.
+
+use crate::CompressedFormat;
+
+/// Decode one mip level into tightly packed pixels of
+/// [`CompressedFormat::fallback_format`]. `None` if there's no decoder for the format.
+pub(crate) fn decompress(
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    let decode_block: fn(&[u8], &mut [[u8; 4]; 16]) = match format {
+        CompressedFormat::Bc1 { alpha, .. } => {
+            if alpha {
+                |block, out| decode_bc1(block, out, true)
+            } else {
+                |block, out| decode_bc1(block, out, false)
+            }
+        }
+        CompressedFormat::Bc2 { .. } => decode_bc2,
+        CompressedFormat::Bc3 { .. } => decode_bc3,
+        CompressedFormat::Bc4 => |block, out| decode_bc4_channel(block, out, 0),
+        CompressedFormat::Bc5 => |block, out| {
+            decode_bc4_channel(&block[..8], out, 0);
+            decode_bc4_channel(&block[8..], out, 1);
+        },
+        CompressedFormat::Etc2Rgb { .. } => decode_etc2_rgb,
+        CompressedFormat::Etc2Rgba { .. } => |block, out| {
+            decode_etc2_rgb(&block[8..], out);
+            decode_eac_alpha(&block[..8], out);
+        },
+        CompressedFormat::EacR11 { signed } => {
+            return decode_floats(format, width, height, data, |block, out| {
+                decode_eac11(block, out, 0, signed);
+            });
+        }
+        CompressedFormat::EacRg11 { signed } => {
+            return decode_floats(format, width, height, data, |block, out| {
+                decode_eac11(&block[..8], out, 0, signed);
+                decode_eac11(&block[8..], out, 1, signed);
+            });
+        }
+        _ => return None,
+    };
+
+    decode_blocks(format, width, height, data, [0, 0, 0, 255], decode_block)
+}
+
+/// Decode a format whose fallback stores floats, into their native endian bytes.
+fn decode_floats(
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    decode_block: impl Fn(&[u8], &mut [[f32; 4]; 16]),
+) -> Option<Vec<u8>> {
+    let pixels = decode_blocks(format, width, height, data, [0.0; 4], decode_block)?;
+    Some(pixels.into_iter().flat_map(f32::to_ne_bytes).collect())
+}
+
+fn decode_blocks<T: Copy>(
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+    empty: [T; 4],
+    decode_block: impl Fn(&[u8], &mut [[T; 4]; 16]),
+) -> Option<Vec<T>> {
+    let channels = format.fallback_format()?.channels();
+    let block_bytes = format.block_bytes();
+    let (blocks_x, blocks_y) = (width.div_ceil(4) as usize, height.div_ceil(4) as usize);
+    if data.len() < blocks_x * blocks_y * block_bytes {
+        return None;
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let mut pixels = vec![empty[0]; width * height * channels];
+
+    for (index, block) in data
+        .chunks_exact(block_bytes)
+        .take(blocks_x * blocks_y)
+        .enumerate()
+    {
+        let mut texels = [empty; 16];
+        decode_block(block, &mut texels);
+
+        let (block_x, block_y) = (index % blocks_x * 4, index / blocks_x * 4);
+        // blocks on the right and bottom edge hang over the image
+        for y in 0..4.min(height - block_y) {
+            for x in 0..4.min(width - block_x) {
+                let offset = ((block_y + y) * width + block_x + x) * channels;
+                pixels[offset..offset + channels].copy_from_slice(&texels[y * 4 + x][..channels]);
+            }
+        }
+    }
+
+    Some(pixels)
+}
+
+fn rgb565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) as u8 & 31;
+    let g = (color >> 5) as u8 & 63;
+    let b = color as u8 & 31;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+/// Decode a BC1 color block. BC2 and BC3 always use the four color mode.
+fn decode_bc1_colors(block: &[u8], out: &mut [[u8; 4]; 16], four_color: bool, alpha: bool) {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+
+    let mix = |wa: u16, wb: u16| {
+        let total = wa + wb;
+        let channel = |i: usize| ((a[i] as u16 * wa + b[i] as u16 * wb) / total) as u8;
+        [channel(0), channel(1), channel(2), 255]
+    };
+
+    let palette = if four_color || c0 > c1 {
+        [
+            [a[0], a[1], a[2], 255],
+            [b[0], b[1], b[2], 255],
+            mix(2, 1),
+            mix(1, 2),
+        ]
+    } else {
+        let transparent = if alpha { 0 } else { 255 };
+        [
+            [a[0], a[1], a[2], 255],
+            [b[0], b[1], b[2], 255],
+            mix(1, 1),
+            [0, 0, 0, transparent],
+        ]
+    };
+
+    for (i, texel) in out.iter_mut().enumerate() {
+        let color = palette[(indices >> (2 * i)) as usize & 3];
+        texel[..3].copy_from_slice(&color[..3]);
+        if !four_color {
+            texel[3] = color[3];
+        }
+    }
+}
+
+fn decode_bc1(block: &[u8], out: &mut [[u8; 4]; 16], alpha: bool) {
+    decode_bc1_colors(block, out, false, alpha);
+}
+
+fn decode_bc2(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    decode_bc1_colors(&block[8..], out, true, false);
+
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    for (i, texel) in out.iter_mut().enumerate() {
+        texel[3] = ((alpha >> (4 * i)) as u8 & 15) * 17;
+    }
+}
+
+fn decode_bc3(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    decode_bc1_colors(&block[8..], out, true, false);
+    decode_bc4_channel(&block[..8], out, 3);
+}
+
+/// Decode an 8 byte BC4 block, which is also the alpha block of BC3, into one channel.
+fn decode_bc4_channel(block: &[u8], out: &mut [[u8; 4]; 16], channel: usize) {
+    let (a0, a1) = (block[0] as u16, block[1] as u16);
+    let mut palette = [0_u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = (((7 - i as u16) * a0 + i as u16 * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (((5 - i as u16) * a0 + i as u16 * a1) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut bytes = [0_u8; 8];
+    bytes[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bytes);
+    for (i, texel) in out.iter_mut().enumerate() {
+        texel[channel] = palette[(indices >> (3 * i)) as usize & 7];
+    }
+}
+
+/// Bits `high..=low` of an ETC2 block, which is stored big endian.
+const fn bits(word: u64, high: u32, low: u32) -> u32 {
+    ((word >> low) & ((1 << (high - low + 1)) - 1)) as u32
+}
+
+const fn extend(value: u32, from_bits: u32) -> i32 {
+    ((value << (8 - from_bits)) | (value >> (2 * from_bits - 8))) as i32
+}
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+fn rgb(r: i32, g: i32, b: i32) -> [u8; 3] {
+    [
+        r.clamp(0, 255) as u8,
+        g.clamp(0, 255) as u8,
+        b.clamp(0, 255) as u8,
+    ]
+}
+
+fn decode_etc2_rgb(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let word = u64::from_be_bytes(block[..8].try_into().unwrap());
+    // pixels are numbered column by column, the low half holds their index bits
+    let index = |x: usize, y: usize| {
+        let i = x * 4 + y;
+        (bits(word, 16 + i as u32, 16 + i as u32) << 1 | bits(word, i as u32, i as u32)) as usize
+    };
+    let mut put = |x: usize, y: usize, color: [u8; 3]| out[y * 4 + x][..3].copy_from_slice(&color);
+
+    if bits(word, 33, 33) == 0 {
+        let base = [
+            [bits(word, 63, 60), bits(word, 55, 52), bits(word, 47, 44)],
+            [bits(word, 59, 56), bits(word, 51, 48), bits(word, 43, 40)],
+        ]
+        .map(|c| c.map(|v| extend(v, 4)));
+        return decode_etc1_subblocks(word, base, index, put);
+    }
+
+    let r = bits(word, 63, 59) as i32;
+    let g = bits(word, 55, 51) as i32;
+    let b = bits(word, 47, 43) as i32;
+    // the deltas are 3 bit two's complement
+    let delta = |v: u32| ((v << 29) as i32) >> 29;
+    let r2 = r + delta(bits(word, 58, 56));
+    let g2 = g + delta(bits(word, 50, 48));
+    let b2 = b + delta(bits(word, 42, 40));
+
+    if !(0..32).contains(&r2) {
+        // T mode
+        let c1 = [
+            bits(word, 60, 59) << 2 | bits(word, 57, 56),
+            bits(word, 55, 52),
+            bits(word, 51, 48),
+        ]
+        .map(|v| extend(v, 4));
+        let c2 = [bits(word, 47, 44), bits(word, 43, 40), bits(word, 39, 36)].map(|v| extend(v, 4));
+        let d = ETC_DISTANCES[(bits(word, 35, 34) << 1 | bits(word, 32, 32)) as usize];
+
+        let paint = [
+            rgb(c1[0], c1[1], c1[2]),
+            rgb(c2[0] + d, c2[1] + d, c2[2] + d),
+            rgb(c2[0], c2[1], c2[2]),
+            rgb(c2[0] - d, c2[1] - d, c2[2] - d),
+        ];
+        for y in 0..4 {
+            for x in 0..4 {
+                put(x, y, paint[index(x, y)]);
+            }
+        }
+    } else if !(0..32).contains(&g2) {
+        // H mode
+        let c1 = [
+            bits(word, 62, 59),
+            bits(word, 58, 56) << 1 | bits(word, 52, 52),
+            bits(word, 51, 51) << 3 | bits(word, 49, 47),
+        ];
+        let c2 = [bits(word, 46, 43), bits(word, 42, 39), bits(word, 38, 35)];
+        let order = c1[0] << 8 | c1[1] << 4 | c1[2] >= c2[0] << 8 | c2[1] << 4 | c2[2];
+        let d = ETC_DISTANCES
+            [(bits(word, 34, 34) << 2 | bits(word, 32, 32) << 1 | order as u32) as usize];
+        let (c1, c2) = (c1.map(|v| extend(v, 4)), c2.map(|v| extend(v, 4)));
+
+        let paint = [
+            rgb(c1[0] + d, c1[1] + d, c1[2] + d),
+            rgb(c1[0] - d, c1[1] - d, c1[2] - d),
+            rgb(c2[0] + d, c2[1] + d, c2[2] + d),
+            rgb(c2[0] - d, c2[1] - d, c2[2] - d),
+        ];
+        for y in 0..4 {
+            for x in 0..4 {
+                put(x, y, paint[index(x, y)]);
+            }
+        }
+    } else if !(0..32).contains(&b2) {
+        // planar mode, a gradient between three colors
+        let origin = [
+            extend(bits(word, 62, 57), 6),
+            extend(bits(word, 56, 56) << 6 | bits(word, 54, 49), 7),
+            extend(
+                bits(word, 48, 48) << 5 | bits(word, 44, 43) << 3 | bits(word, 41, 39),
+                6,
+            ),
+        ];
+        let horizontal = [
+            extend(bits(word, 38, 34) << 1 | bits(word, 32, 32), 6),
+            extend(bits(word, 31, 25), 7),
+            extend(bits(word, 24, 19), 6),
+        ];
+        let vertical = [
+            extend(bits(word, 18, 13), 6),
+            extend(bits(word, 12, 6), 7),
+            extend(bits(word, 5, 0), 6),
+        ];
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let channel = |c: usize| {
+                    (x as i32 * (horizontal[c] - origin[c])
+                        + y as i32 * (vertical[c] - origin[c])
+                        + 4 * origin[c]
+                        + 2)
+                        >> 2
+                };
+                put(x, y, rgb(channel(0), channel(1), channel(2)));
+            }
+        }
+    } else {
+        let base = [[r, g, b], [r2, g2, b2]].map(|c| c.map(|v| extend(v as u32, 5)));
+        decode_etc1_subblocks(word, base, index, put);
+    }
+}
+
+/// The individual and differential modes, two sub blocks with a base color each.
+fn decode_etc1_subblocks(
+    word: u64,
+    base: [[i32; 3]; 2],
+    index: impl Fn(usize, usize) -> usize,
+    mut put: impl FnMut(usize, usize, [u8; 3]),
+) {
+    let tables = [bits(word, 39, 37) as usize, bits(word, 36, 34) as usize];
+    let flip = bits(word, 32, 32) == 1;
+
+    for y in 0..4 {
+        for x in 0..4 {
+            let sub = if flip { y / 2 } else { x / 2 };
+            let [small, large] = ETC_MODIFIERS[tables[sub]];
+            let modifier = [small, large, -small, -large][index(x, y)];
+            let [r, g, b] = base[sub];
+            put(x, y, rgb(r + modifier, g + modifier, b + modifier));
+        }
+    }
+}
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// The 3 bit modifier index of a pixel in an EAC block, stored column by column.
+fn eac_index(word: u64, x: usize, y: usize) -> usize {
+    let high = 47 - 3 * (x * 4 + y) as u32;
+    bits(word, high, high - 2) as usize
+}
+
+fn decode_eac_alpha(block: &[u8], out: &mut [[u8; 4]; 16]) {
+    let word = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let base = bits(word, 63, 56) as i32;
+    let multiplier = bits(word, 55, 52) as i32;
+    let table = EAC_MODIFIERS[bits(word, 51, 48) as usize];
+
+    for x in 0..4 {
+        for y in 0..4 {
+            let modifier = table[eac_index(word, x, y)];
+            out[y * 4 + x][3] = (base + modifier * multiplier).clamp(0, 255) as u8;
+        }
+    }
+}
+
+/// An R11 block into `channel`, normalized to `0.0..=1.0`, or `-1.0..=1.0` if `signed`.
+fn decode_eac11(block: &[u8], out: &mut [[f32; 4]; 16], channel: usize, signed: bool) {
+    let word = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let multiplier = bits(word, 55, 52) as i32;
+    let table = EAC_MODIFIERS[bits(word, 51, 48) as usize];
+    // a multiplier of 0 stands for 1/8
+    let scale = |modifier: i32| match multiplier {
+        0 => modifier,
+        _ => modifier * multiplier * 8,
+    };
+
+    for x in 0..4 {
+        for y in 0..4 {
+            let modifier = table[eac_index(word, x, y)];
+            out[y * 4 + x][channel] = if signed {
+                // -128 is treated as -127, so the range is symmetric
+                let base = (bits(word, 63, 56) as u8 as i8).max(-127) as i32;
+                (base * 8 + scale(modifier)).clamp(-1023, 1023) as f32 / 1023.0
+            } else {
+                let base = bits(word, 63, 56) as i32;
+                (base * 8 + 4 + scale(modifier)).clamp(0, 2047) as f32 / 2047.0
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED_565: [u8; 2] = 0xF800_u16.to_le_bytes();
+    const BLUE_565: [u8; 2] = 0x001F_u16.to_le_bytes();
+
+    fn bc1(c0: [u8; 2], c1: [u8; 2], indices: u32) -> Vec<u8> {
+        [&c0[..], &c1, &indices.to_le_bytes()].concat()
+    }
+
+    fn pixels(data: &[u8], channels: usize) -> Vec<&[u8]> {
+        data.chunks_exact(channels).collect()
+    }
+
+    #[test]
+    fn bc1_solid() {
+        let block = bc1(RED_565, BLUE_565, 0);
+        let format = CompressedFormat::Bc1 {
+            alpha: false,
+            srgb: false,
+        };
+        let decoded = decompress(format, 4, 4, &block).unwrap();
+        assert!(pixels(&decoded, 4).iter().all(|p| *p == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn bc1_interpolated() {
+        // every texel uses index 2, two thirds red and one third blue
+        let block = bc1(RED_565, BLUE_565, 0xAAAA_AAAA);
+        let format = CompressedFormat::Bc1 {
+            alpha: false,
+            srgb: false,
+        };
+        let decoded = decompress(format, 4, 4, &block).unwrap();
+        assert_eq!(&decoded[..4], [170, 0, 85, 255]);
+    }
+
+    #[test]
+    fn bc1_punch_through_alpha() {
+        // c0 <= c1 selects the three color mode, index 3 is transparent black
+        let block = bc1(BLUE_565, RED_565, 0xFFFF_FFFF);
+        let with_alpha = CompressedFormat::Bc1 {
+            alpha: true,
+            srgb: false,
+        };
+        let decoded = decompress(with_alpha, 4, 4, &block).unwrap();
+        assert_eq!(&decoded[..4], [0, 0, 0, 0]);
+
+        let without_alpha = CompressedFormat::Bc1 {
+            alpha: false,
+            srgb: false,
+        };
+        let decoded = decompress(without_alpha, 4, 4, &block).unwrap();
+        assert_eq!(&decoded[..4], [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn bc2_alpha() {
+        let alpha = 0x0123_4567_89AB_CDEF_u64.to_le_bytes();
+        let block = [&alpha[..], &bc1(RED_565, RED_565, 0)].concat();
+        let decoded = decompress(CompressedFormat::Bc2 { srgb: false }, 4, 4, &block).unwrap();
+        let alphas: Vec<u8> = pixels(&decoded, 4).iter().map(|p| p[3]).collect();
+        let expected: Vec<u8> = (0..16).rev().map(|nibble| nibble * 17).collect();
+        assert_eq!(alphas, expected);
+    }
+
+    #[test]
+    fn bc4_endpoints() {
+        // index 0 is the first endpoint, index 1 the second
+        let mut block = [200, 100, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            decompress(CompressedFormat::Bc4, 4, 4, &block).unwrap(),
+            vec![200; 16]
+        );
+
+        block[2] = 0b001;
+        let decoded = decompress(CompressedFormat::Bc4, 4, 4, &block).unwrap();
+        assert_eq!(&decoded[..2], [100, 200]);
+    }
+
+    #[test]
+    fn bc5_two_channels() {
+        let block = [10, 10, 0, 0, 0, 0, 0, 0, 20, 20, 0, 0, 0, 0, 0, 0];
+        let decoded = decompress(CompressedFormat::Bc5, 4, 4, &block).unwrap();
+        assert!(pixels(&decoded, 2).iter().all(|p| *p == [10, 20]));
+    }
+
+    #[test]
+    fn partial_blocks() {
+        // a 5x3 image needs two blocks, only the covered texels are kept
+        let blocks = [bc1(RED_565, RED_565, 0), bc1(BLUE_565, BLUE_565, 0)].concat();
+        let format = CompressedFormat::Bc1 {
+            alpha: false,
+            srgb: false,
+        };
+        let decoded = decompress(format, 5, 3, &blocks).unwrap();
+        assert_eq!(decoded.len(), 5 * 3 * 4);
+        let row: Vec<&[u8]> = pixels(&decoded[..5 * 4], 4);
+        assert_eq!(row[3], [255, 0, 0, 255]);
+        assert_eq!(row[4], [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn truncated() {
+        let format = CompressedFormat::Bc1 {
+            alpha: false,
+            srgb: false,
+        };
+        assert!(decompress(format, 8, 4, &[0; 8]).is_none());
+    }
+
+    #[test]
+    fn no_decoder() {
+        assert!(decompress(CompressedFormat::Bc7 { srgb: false }, 4, 4, &[0; 16]).is_none());
+    }
+
+    const ETC2: CompressedFormat = CompressedFormat::Etc2Rgb { srgb: false };
+
+    /// The color of the pixel at `x`, `y` of a decoded ETC2 block.
+    fn etc2_pixel(block: [u8; 8], x: usize, y: usize) -> [u8; 3] {
+        let decoded = decompress(ETC2, 4, 4, &block).unwrap();
+        let offset = (y * 4 + x) * 4;
+        decoded[offset..offset + 3].try_into().unwrap()
+    }
+
+    /// An EAC block, `indices` in row order.
+    fn eac(base: u8, multiplier: u8, table: u8, indices: [u8; 16]) -> [u8; 8] {
+        let mut word = (base as u64) << 56 | (multiplier as u64) << 52 | (table as u64) << 48;
+        for x in 0..4 {
+            for y in 0..4 {
+                word |= (indices[y * 4 + x] as u64) << (45 - 3 * (x * 4 + y));
+            }
+        }
+        word.to_be_bytes()
+    }
+
+    fn floats(data: &[u8]) -> Vec<f32> {
+        data.chunks_exact(4)
+            .map(|bytes| f32::from_ne_bytes(bytes.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn etc2_individual() {
+        // red on the left half, blue on the right, every modifier +2
+        // except the pixel at (1, 2), which gets -8
+        let block = [0xF0, 0x00, 0x0F, 0x00, 0x00, 0x40, 0x00, 0x40];
+        assert_eq!(etc2_pixel(block, 0, 0), [255, 2, 2]);
+        assert_eq!(etc2_pixel(block, 1, 2), [247, 0, 0]);
+        assert_eq!(etc2_pixel(block, 3, 3), [2, 2, 255]);
+    }
+
+    #[test]
+    fn etc2_differential() {
+        // flipped, the base colors 16,8,0 and 19,4,0 with the tables 1 and 2
+        let block = [0x83, 0x44, 0x00, 0x2B, 0, 0, 0, 0];
+        assert_eq!(etc2_pixel(block, 3, 1), [137, 71, 5]);
+        assert_eq!(etc2_pixel(block, 0, 2), [165, 42, 9]);
+    }
+
+    #[test]
+    fn etc2_t_mode() {
+        // the first row uses every paint color once
+        let block = [0x1C, 0x36, 0x88, 0x8B, 0x11, 0x00, 0x10, 0x10];
+        assert_eq!(etc2_pixel(block, 0, 0), [204, 51, 102]);
+        assert_eq!(etc2_pixel(block, 1, 0), [168, 168, 168]);
+        assert_eq!(etc2_pixel(block, 2, 0), [136, 136, 136]);
+        assert_eq!(etc2_pixel(block, 3, 0), [104, 104, 104]);
+    }
+
+    #[test]
+    fn etc2_h_mode() {
+        let block = [0x52, 0x15, 0x92, 0x33, 0x11, 0x00, 0x10, 0x10];
+        assert_eq!(etc2_pixel(block, 0, 0), [186, 101, 67]);
+        assert_eq!(etc2_pixel(block, 1, 0), [154, 69, 35]);
+        assert_eq!(etc2_pixel(block, 2, 0), [50, 84, 118]);
+        assert_eq!(etc2_pixel(block, 3, 0), [18, 52, 86]);
+    }
+
+    #[test]
+    fn etc2_planar() {
+        // black at the origin, red to the right and blue to the bottom
+        let block = [0x00, 0x00, 0x04, 0x7F, 0x00, 0x00, 0x00, 0x3F];
+        assert_eq!(etc2_pixel(block, 0, 0), [0, 0, 0]);
+        assert_eq!(etc2_pixel(block, 1, 0), [64, 0, 0]);
+        assert_eq!(etc2_pixel(block, 2, 1), [128, 0, 64]);
+        assert_eq!(etc2_pixel(block, 3, 3), [191, 0, 191]);
+    }
+
+    #[test]
+    fn eac_alpha() {
+        let mut indices = [4; 16];
+        indices[0] = 7;
+        indices[5] = 3;
+        let block = [&eac(100, 2, 13, indices)[..], &[0; 8]].concat();
+        let format = CompressedFormat::Etc2Rgba { srgb: false };
+        let alphas: Vec<u8> = pixels(&decompress(format, 4, 4, &block).unwrap(), 4)
+            .iter()
+            .map(|p| p[3])
+            .collect();
+        assert_eq!(alphas[0], 118);
+        assert_eq!(alphas[5], 80);
+        assert!(alphas[1..5].iter().all(|a| *a == 100));
+
+        let block = [&eac(250, 15, 0, [7; 16])[..], &[0; 8]].concat();
+        let decoded = decompress(format, 4, 4, &block).unwrap();
+        assert_eq!(decoded[3], 255);
+    }
+
+    #[test]
+    fn eac_r11() {
+        let format = CompressedFormat::EacR11 { signed: false };
+        let decoded = floats(&decompress(format, 4, 4, &eac(128, 1, 0, [0; 16])).unwrap());
+        assert_eq!(decoded, vec![1004.0 / 2047.0; 16]);
+
+        // a multiplier of 0 adds the modifier unscaled
+        let decoded = floats(&decompress(format, 4, 4, &eac(128, 0, 0, [7; 16])).unwrap());
+        assert_eq!(decoded[0], 1042.0 / 2047.0);
+
+        let decoded = floats(&decompress(format, 4, 4, &eac(255, 15, 0, [7; 16])).unwrap());
+        assert_eq!(decoded[0], 1.0);
+    }
+
+    #[test]
+    fn eac_signed_r11() {
+        let format = CompressedFormat::EacR11 { signed: true };
+        // -100 * 8 + 14 * 2 * 8
+        let decoded = floats(&decompress(format, 4, 4, &eac(0x9C, 2, 0, [7; 16])).unwrap());
+        assert_eq!(decoded[0], -576.0 / 1023.0);
+
+        // -128 reads as -127, then it's clamped to -1023
+        let decoded = floats(&decompress(format, 4, 4, &eac(0x80, 15, 0, [3; 16])).unwrap());
+        assert_eq!(decoded[0], -1.0);
+        let decoded = floats(&decompress(format, 4, 4, &eac(0x80, 0, 13, [4; 16])).unwrap());
+        assert_eq!(decoded[0], -1016.0 / 1023.0);
+    }
+
+    #[test]
+    fn eac_rg11() {
+        let block = [eac(0, 0, 0, [0; 16]), eac(255, 0, 0, [4; 16])].concat();
+        let decoded =
+            floats(&decompress(CompressedFormat::EacRg11 { signed: false }, 4, 4, &block).unwrap());
+        assert_eq!(decoded.len(), 32);
+        assert_eq!(&decoded[..2], [1.0 / 2047.0, 2046.0 / 2047.0]);
+
+        let block = [eac(0x40, 0, 14, [7; 16]), eac(0xC0, 0, 14, [3; 16])].concat();
+        let decoded =
+            floats(&decompress(CompressedFormat::EacRg11 { signed: true }, 4, 4, &block).unwrap());
+        assert_eq!(&decoded[..2], [520.0 / 1023.0, -521.0 / 1023.0]);
+    }
+}
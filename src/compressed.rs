@@ -0,0 +1,486 @@
+use crate::{
+    CompressedImage, SamplerDesc, TextureFormat, TextureObject, TextureUnits, block_decode,
+    compressed_image::check_levels,
+};
+use core::{error, fmt};
+use std::{collections::HashSet, ffi::CStr, ffi::c_void, fmt::Debug, ptr};
+
+// S3TC and ASTC are extensions, so the generated bindings don't have them
+const COMPRESSED_RGB_S3TC_DXT1: u32 = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: u32 = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: u32 = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: u32 = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: u32 = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: u32 = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: u32 = 0x8C4F;
+const COMPRESSED_RGBA_ASTC_4X4: u32 = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: u32 = 0x93D0;
+
+/// A block compressed texture format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// DXT1, with 1 bit alpha if `alpha` is set.
+    Bc1 {
+        alpha: bool,
+        srgb: bool,
+    },
+    /// DXT3.
+    Bc2 {
+        srgb: bool,
+    },
+    /// DXT5.
+    Bc3 {
+        srgb: bool,
+    },
+    /// One channel.
+    Bc4,
+    /// Two channels, usually normal maps.
+    Bc5,
+    /// Unsigned HDR color.
+    Bc6h,
+    Bc7 {
+        srgb: bool,
+    },
+    Etc2Rgb {
+        srgb: bool,
+    },
+    /// ETC2 with EAC alpha.
+    Etc2Rgba {
+        srgb: bool,
+    },
+    /// One 11 bit channel.
+    EacR11 {
+        signed: bool,
+    },
+    /// Two 11 bit channels.
+    EacRg11 {
+        signed: bool,
+    },
+    /// `block` is one of [`CompressedFormat::ASTC_BLOCKS`].
+    Astc {
+        block: (u8, u8),
+        srgb: bool,
+    },
+}
+
+impl CompressedFormat {
+    /// The ASTC block sizes, in the order of their GL enums.
+    pub const ASTC_BLOCKS: [(u8, u8); 14] = [
+        (4, 4),
+        (5, 4),
+        (5, 5),
+        (6, 5),
+        (6, 6),
+        (8, 5),
+        (8, 6),
+        (8, 8),
+        (10, 5),
+        (10, 6),
+        (10, 8),
+        (10, 10),
+        (12, 10),
+        (12, 12),
+    ];
+
+    /// The GL internal format, e.g. `gl::COMPRESSED_RGBA_BPTC_UNORM` for BC7.
+    pub fn gl_format(self) -> u32 {
+        match self {
+            Self::Bc1 { alpha, srgb } => match (alpha, srgb) {
+                (false, false) => COMPRESSED_RGB_S3TC_DXT1,
+                (true, false) => COMPRESSED_RGBA_S3TC_DXT1,
+                (false, true) => COMPRESSED_SRGB_S3TC_DXT1,
+                (true, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+            },
+            Self::Bc2 { srgb: false } => COMPRESSED_RGBA_S3TC_DXT3,
+            Self::Bc2 { srgb: true } => COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+            Self::Bc3 { srgb: false } => COMPRESSED_RGBA_S3TC_DXT5,
+            Self::Bc3 { srgb: true } => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+            Self::Bc4 => gl::COMPRESSED_RED_RGTC1,
+            Self::Bc5 => gl::COMPRESSED_RG_RGTC2,
+            Self::Bc6h => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            Self::Bc7 { srgb: false } => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            Self::Bc7 { srgb: true } => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            Self::Etc2Rgb { srgb: false } => gl::COMPRESSED_RGB8_ETC2,
+            Self::Etc2Rgb { srgb: true } => gl::COMPRESSED_SRGB8_ETC2,
+            Self::Etc2Rgba { srgb: false } => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            Self::Etc2Rgba { srgb: true } => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            Self::EacR11 { signed: false } => gl::COMPRESSED_R11_EAC,
+            Self::EacR11 { signed: true } => gl::COMPRESSED_SIGNED_R11_EAC,
+            Self::EacRg11 { signed: false } => gl::COMPRESSED_RG11_EAC,
+            Self::EacRg11 { signed: true } => gl::COMPRESSED_SIGNED_RG11_EAC,
+            Self::Astc { block, srgb } => {
+                let index = Self::ASTC_BLOCKS
+                    .iter()
+                    .position(|b| *b == block)
+                    .unwrap_or(0) as u32;
+                if srgb {
+                    COMPRESSED_SRGB8_ALPHA8_ASTC_4X4 + index
+                } else {
+                    COMPRESSED_RGBA_ASTC_4X4 + index
+                }
+            }
+        }
+    }
+
+    /// The size of a block in pixels.
+    pub fn block_size(self) -> (u32, u32) {
+        match self {
+            Self::Astc { block, .. } => (block.0 as u32, block.1 as u32),
+            _ => (4, 4),
+        }
+    }
+
+    /// The size of a block in bytes.
+    pub fn block_bytes(self) -> usize {
+        match self {
+            Self::Bc1 { .. } | Self::Bc4 | Self::Etc2Rgb { .. } | Self::EacR11 { .. } => 8,
+            _ => 16,
+        }
+    }
+
+    /// The size in bytes of a `width` by `height` image.
+    pub fn image_size(self, width: u32, height: u32) -> usize {
+        let (block_width, block_height) = self.block_size();
+        width.div_ceil(block_width) as usize
+            * height.div_ceil(block_height) as usize
+            * self.block_bytes()
+    }
+
+    pub fn is_srgb(self) -> bool {
+        match self {
+            Self::Bc1 { srgb, .. }
+            | Self::Bc2 { srgb }
+            | Self::Bc3 { srgb }
+            | Self::Bc7 { srgb }
+            | Self::Etc2Rgb { srgb }
+            | Self::Etc2Rgba { srgb }
+            | Self::Astc { srgb, .. } => srgb,
+            Self::Bc4 | Self::Bc5 | Self::Bc6h | Self::EacR11 { .. } | Self::EacRg11 { .. } => {
+                false
+            }
+        }
+    }
+
+    /// The format the CPU fallback decompresses to, `None` if there is no fallback.
+    /// BC6H, BC7 and ASTC have none.
+    pub fn fallback_format(self) -> Option<TextureFormat> {
+        match self {
+            Self::Bc4 => Some(TextureFormat::R8),
+            Self::Bc5 => Some(TextureFormat::Rg8),
+            // floats keep the 11 bits and the sign
+            Self::EacR11 { .. } => Some(TextureFormat::R32f),
+            Self::EacRg11 { .. } => Some(TextureFormat::Rg32f),
+            Self::Bc6h | Self::Bc7 { .. } | Self::Astc { .. } => None,
+            _ if self.is_srgb() => Some(TextureFormat::Srgb8Alpha8),
+            _ => Some(TextureFormat::Rgba8),
+        }
+    }
+}
+
+/// Which compressed formats the driver can sample, queried once per context.
+#[derive(Debug, Clone)]
+pub struct CompressionSupport {
+    formats: HashSet<u32>,
+    extensions: HashSet<String>,
+}
+
+impl CompressionSupport {
+    /// Query the current context.
+    pub fn query() -> Self {
+        let mut count = 0;
+        unsafe { gl::GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, ptr::addr_of_mut!(count)) };
+        let mut formats = vec![0_i32; count.max(0) as usize];
+        if !formats.is_empty() {
+            unsafe { gl::GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr()) };
+        }
+
+        let mut count = 0;
+        unsafe { gl::GetIntegerv(gl::NUM_EXTENSIONS, ptr::addr_of_mut!(count)) };
+        let extensions = (0..count.max(0) as u32)
+            .filter_map(|index| unsafe {
+                let name = gl::GetStringi(gl::EXTENSIONS, index);
+                (!name.is_null()).then(|| {
+                    CStr::from_ptr(name as *const _)
+                        .to_string_lossy()
+                        .into_owned()
+                })
+            })
+            .collect();
+
+        Self {
+            formats: formats.into_iter().map(|f| f as u32).collect(),
+            extensions,
+        }
+    }
+
+    /// Support that has nothing but the CPU fallback.
+    pub fn none() -> Self {
+        Self {
+            formats: HashSet::new(),
+            extensions: HashSet::new(),
+        }
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+
+    pub fn bc(&self) -> bool {
+        self.supports(CompressedFormat::Bc3 { srgb: false })
+    }
+
+    pub fn etc2(&self) -> bool {
+        self.supports(CompressedFormat::Etc2Rgb { srgb: false })
+    }
+
+    pub fn astc(&self) -> bool {
+        self.supports(CompressedFormat::Astc {
+            block: (4, 4),
+            srgb: false,
+        })
+    }
+
+    /// Whether textures of `format` can be uploaded without decompressing them.
+    pub fn supports(&self, format: CompressedFormat) -> bool {
+        // drivers don't have to list every format they accept, so the
+        // extensions are checked too
+        self.formats.contains(&format.gl_format())
+            || match format {
+                CompressedFormat::Bc1 { srgb, .. }
+                | CompressedFormat::Bc2 { srgb }
+                | CompressedFormat::Bc3 { srgb } => {
+                    self.has_extension("GL_EXT_texture_compression_s3tc")
+                        && (!srgb || self.has_extension("GL_EXT_texture_sRGB"))
+                }
+                CompressedFormat::Bc4 | CompressedFormat::Bc5 => {
+                    self.has_extension("GL_ARB_texture_compression_rgtc")
+                }
+                CompressedFormat::Bc6h | CompressedFormat::Bc7 { .. } => {
+                    self.has_extension("GL_ARB_texture_compression_bptc")
+                }
+                CompressedFormat::Etc2Rgb { .. }
+                | CompressedFormat::Etc2Rgba { .. }
+                | CompressedFormat::EacR11 { .. }
+                | CompressedFormat::EacRg11 { .. } => {
+                    self.has_extension("GL_ARB_ES3_compatibility")
+                }
+                CompressedFormat::Astc { .. } => {
+                    self.has_extension("GL_KHR_texture_compression_astc_ldr")
+                }
+            }
+    }
+}
+
+#[derive(Debug)]
+pub enum CompressedTextureError {
+    /// The file isn't a valid container, with a description of what's wrong.
+    InvalidContainer(&'static str),
+    /// The container stores a pixel format this crate doesn't know, e.g. a `VkFormat`,
+    /// a `DXGI_FORMAT` or a FourCC code.
+    UnknownFormat(u32),
+    /// The container uses supercompression, like Basis Universal or zstd.
+    Supercompressed(u32),
+    /// The driver can't sample the format and there's no CPU fallback for it.
+    Unsupported(CompressedFormat),
+}
+
+impl fmt::Display for CompressedTextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidContainer(s) => write!(f, "Invalid texture container: {s}"),
+            Self::UnknownFormat(format) => write!(f, "Unknown pixel format: 0x{format:x}"),
+            Self::Supercompressed(scheme) => {
+                write!(f, "Supercompression scheme {scheme} is not supported")
+            }
+            Self::Unsupported(format) => write!(
+                f,
+                "{format:?} is not supported by the driver and can't be decompressed"
+            ),
+        }
+    }
+}
+
+impl error::Error for CompressedTextureError {}
+
+/// A texture uploaded from a [`CompressedImage`]. If the driver can't sample
+/// the format, it holds the decompressed pixels instead.
+pub struct CompressedTexture {
+    id: u32,
+    width: u32,
+    height: u32,
+    format: CompressedFormat,
+    levels: i32,
+    decompressed: bool,
+}
+
+impl CompressedTexture {
+    /// Upload every mip level of `image`, decompressing on the CPU
+    /// if `support` says the driver can't sample it.
+    pub fn new(
+        image: &CompressedImage,
+        support: &CompressionSupport,
+    ) -> Result<Self, CompressedTextureError> {
+        let format = image.format;
+        let decompressed = !support.supports(format);
+        let fallback = format.fallback_format();
+        if decompressed && fallback.is_none() {
+            return Err(CompressedTextureError::Unsupported(format));
+        }
+
+        check_levels(image.width, image.height, image.levels.len())?;
+
+        // decode everything first, so a truncated level doesn't leave a half made texture
+        let decoded = image
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let width = (image.width >> level).max(1);
+                let height = (image.height >> level).max(1);
+                if !decompressed {
+                    return Ok(None);
+                }
+
+                block_decode::decompress(format, width, height, data)
+                    .map(Some)
+                    .ok_or(CompressedTextureError::InvalidContainer(
+                        "truncated mip level",
+                    ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut id = 0_u32;
+        unsafe {
+            gl::GenTextures(1, ptr::addr_of_mut!(id));
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        }
+
+        for (level, (data, pixels)) in image.levels.iter().zip(&decoded).enumerate() {
+            let width = (image.width >> level).max(1);
+            let height = (image.height >> level).max(1);
+
+            if let (Some(pixels), Some(fallback)) = (pixels, fallback) {
+                unsafe {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        level as i32,
+                        fallback as i32,
+                        width as i32,
+                        height as i32,
+                        0,
+                        fallback.pixel_format(),
+                        fallback.pixel_type(),
+                        pixels.as_ptr() as *const c_void,
+                    );
+                }
+            } else {
+                unsafe {
+                    gl::CompressedTexImage2D(
+                        gl::TEXTURE_2D,
+                        level as i32,
+                        format.gl_format(),
+                        width as i32,
+                        height as i32,
+                        0,
+                        data.len() as i32,
+                        data.as_ptr() as *const c_void,
+                    );
+                }
+            }
+        }
+
+        let levels = image.levels.len() as i32;
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            // containers don't always store the full chain
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, levels - 1);
+        }
+
+        let texture = Self {
+            id,
+            width: image.width,
+            height: image.height,
+            format,
+            levels,
+            decompressed,
+        };
+        if levels == 1 {
+            texture.set_sampler(&SamplerDesc::linear().mipmap_filter(None));
+        }
+
+        Ok(texture)
+    }
+
+    /// Parse a KTX2 file and upload it, see [`CompressedTexture::new`].
+    pub fn from_ktx2(
+        bytes: &[u8],
+        support: &CompressionSupport,
+    ) -> Result<Self, CompressedTextureError> {
+        Self::new(&CompressedImage::from_ktx2(bytes)?, support)
+    }
+
+    /// Parse a DDS file and upload it, see [`CompressedTexture::new`].
+    pub fn from_dds(
+        bytes: &[u8],
+        support: &CompressionSupport,
+    ) -> Result<Self, CompressedTextureError> {
+        Self::new(&CompressedImage::from_dds(bytes)?, support)
+    }
+
+    /// Set how the texture is sampled. Binds it to the active texture unit.
+    pub fn set_sampler(&self, desc: &SamplerDesc) {
+        desc.apply_to_texture(gl::TEXTURE_2D, self.id);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> CompressedFormat {
+        self.format
+    }
+
+    pub fn levels(&self) -> i32 {
+        self.levels
+    }
+
+    /// Whether the texture went through the CPU fallback.
+    pub fn is_decompressed(&self) -> bool {
+        self.decompressed
+    }
+}
+
+impl TextureObject for CompressedTexture {
+    fn raw_id(&self) -> u32 {
+        self.id
+    }
+
+    fn target(&self) -> u32 {
+        gl::TEXTURE_2D
+    }
+}
+
+impl Drop for CompressedTexture {
+    fn drop(&mut self) {
+        TextureUnits::forget_texture(self.id);
+        unsafe { gl::DeleteTextures(1, ptr::addr_of!(self.id)) }
+    }
+}
+
+impl Debug for CompressedTexture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedTexture")
+            .field("id", &self.id)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
+            .field("decompressed", &self.decompressed)
+            .finish()
+    }
+}
@@ -0,0 +1,397 @@
+use crate::{CompressedFormat, CompressedTextureError};
+
+/// A block compressed mip chain, read from a container file.
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    pub format: CompressedFormat,
+    pub width: u32,
+    pub height: u32,
+    /// The mip levels, starting with the full size one.
+    pub levels: Vec<Vec<u8>>,
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDPF_FOURCC: u32 = 0x4;
+
+/// Little endian reads that fail with [`CompressedTextureError::InvalidContainer`].
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes(&self, offset: usize, length: usize) -> Result<&[u8], CompressedTextureError> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.0.get(offset..end))
+            .ok_or(CompressedTextureError::InvalidContainer(
+                "unexpected end of file",
+            ))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, CompressedTextureError> {
+        let bytes = self.bytes(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, CompressedTextureError> {
+        Ok(self.u32(offset)? as u64 | (self.u32(offset + 4)? as u64) << 32)
+    }
+}
+
+impl CompressedImage {
+    /// Parse a KTX2 file. Only 2D images without supercompression are supported,
+    /// for arrays and cube maps the first layer or face is used.
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self, CompressedTextureError> {
+        let reader = Reader(bytes);
+        if reader.bytes(0, 12)? != KTX2_IDENTIFIER {
+            return Err(CompressedTextureError::InvalidContainer("not a KTX2 file"));
+        }
+
+        let vk_format = reader.u32(12)?;
+        let format = format_from_vk(vk_format)?;
+        let width = reader.u32(20)?;
+        let height = reader.u32(24)?;
+        if reader.u32(28)? > 1 {
+            return Err(CompressedTextureError::InvalidContainer(
+                "3D textures are not supported",
+            ));
+        }
+        // 0 asks the loader to generate mipmaps, which isn't possible for compressed data
+        let level_count = reader.u32(40)?.max(1) as usize;
+        let supercompression = reader.u32(44)?;
+        if supercompression != 0 {
+            return Err(CompressedTextureError::Supercompressed(supercompression));
+        }
+        check_levels(width, height, level_count)?;
+
+        let levels = (0..level_count)
+            .map(|level| {
+                let index = 80 + level * 24;
+                let offset = reader.u64(index)? as usize;
+                let length = reader.u64(index + 8)? as usize;
+
+                let width = (width >> level).max(1);
+                let height = (height >> level).max(1);
+                // layers and faces follow each other, keep the first one
+                let size = format.image_size(width, height);
+                if size > length {
+                    return Err(CompressedTextureError::InvalidContainer(
+                        "mip level is too small",
+                    ));
+                }
+
+                Ok(reader.bytes(offset, size)?.to_vec())
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+
+    /// Parse a DDS file with a FourCC or DX10 header. For arrays and cube maps
+    /// the first layer or face is used.
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, CompressedTextureError> {
+        let reader = Reader(bytes);
+        if reader.bytes(0, 4)? != DDS_MAGIC || reader.u32(4)? != 124 {
+            return Err(CompressedTextureError::InvalidContainer("not a DDS file"));
+        }
+
+        let flags = reader.u32(8)?;
+        let height = reader.u32(12)?;
+        let width = reader.u32(16)?;
+        let level_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+            reader.u32(28)?.max(1) as usize
+        } else {
+            1
+        };
+
+        if reader.u32(80)? & DDPF_FOURCC == 0 {
+            return Err(CompressedTextureError::InvalidContainer(
+                "uncompressed DDS files are not supported",
+            ));
+        }
+
+        let four_cc = reader.u32(84)?;
+        let (format, mut offset) = if &four_cc.to_le_bytes() == b"DX10" {
+            (format_from_dxgi(reader.u32(128)?)?, 148)
+        } else {
+            (format_from_four_cc(four_cc)?, 128)
+        };
+        check_levels(width, height, level_count)?;
+
+        let levels = (0..level_count)
+            .map(|level| {
+                let size = format.image_size((width >> level).max(1), (height >> level).max(1));
+                let data = reader.bytes(offset, size)?.to_vec();
+                offset += size;
+                Ok(data)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            format,
+            width,
+            height,
+            levels,
+        })
+    }
+}
+
+/// Check that the image isn't empty and that there are at most as many levels
+/// as a full mip chain has, before anything is shifted by the level.
+pub(crate) fn check_levels(
+    width: u32,
+    height: u32,
+    level_count: usize,
+) -> Result<(), CompressedTextureError> {
+    if width == 0 || height == 0 {
+        return Err(CompressedTextureError::InvalidContainer("empty image"));
+    }
+    if level_count == 0 {
+        return Err(CompressedTextureError::InvalidContainer("no mip levels"));
+    }
+    if level_count > (32 - width.max(height).leading_zeros()) as usize {
+        return Err(CompressedTextureError::InvalidContainer(
+            "too many mip levels",
+        ));
+    }
+
+    Ok(())
+}
+
+fn format_from_vk(vk_format: u32) -> Result<CompressedFormat, CompressedTextureError> {
+    let srgb = vk_format.is_multiple_of(2);
+    let format = match vk_format {
+        131 | 132 => CompressedFormat::Bc1 { alpha: false, srgb },
+        133 | 134 => CompressedFormat::Bc1 { alpha: true, srgb },
+        135 | 136 => CompressedFormat::Bc2 { srgb },
+        137 | 138 => CompressedFormat::Bc3 { srgb },
+        139 => CompressedFormat::Bc4,
+        141 => CompressedFormat::Bc5,
+        143 => CompressedFormat::Bc6h,
+        145 | 146 => CompressedFormat::Bc7 { srgb },
+        147 | 148 => CompressedFormat::Etc2Rgb { srgb },
+        151 | 152 => CompressedFormat::Etc2Rgba { srgb },
+        153 | 154 => CompressedFormat::EacR11 {
+            signed: vk_format == 154,
+        },
+        155 | 156 => CompressedFormat::EacRg11 {
+            signed: vk_format == 156,
+        },
+        157..=184 => CompressedFormat::Astc {
+            block: CompressedFormat::ASTC_BLOCKS[(vk_format - 157) as usize / 2],
+            srgb,
+        },
+        _ => return Err(CompressedTextureError::UnknownFormat(vk_format)),
+    };
+
+    Ok(format)
+}
+
+fn format_from_dxgi(dxgi_format: u32) -> Result<CompressedFormat, CompressedTextureError> {
+    let format = match dxgi_format {
+        71 => CompressedFormat::Bc1 {
+            alpha: true,
+            srgb: false,
+        },
+        72 => CompressedFormat::Bc1 {
+            alpha: true,
+            srgb: true,
+        },
+        74 => CompressedFormat::Bc2 { srgb: false },
+        75 => CompressedFormat::Bc2 { srgb: true },
+        77 => CompressedFormat::Bc3 { srgb: false },
+        78 => CompressedFormat::Bc3 { srgb: true },
+        80 => CompressedFormat::Bc4,
+        83 => CompressedFormat::Bc5,
+        95 => CompressedFormat::Bc6h,
+        98 => CompressedFormat::Bc7 { srgb: false },
+        99 => CompressedFormat::Bc7 { srgb: true },
+        _ => return Err(CompressedTextureError::UnknownFormat(dxgi_format)),
+    };
+
+    Ok(format)
+}
+
+fn format_from_four_cc(four_cc: u32) -> Result<CompressedFormat, CompressedTextureError> {
+    let format = match &four_cc.to_le_bytes() {
+        b"DXT1" => CompressedFormat::Bc1 {
+            alpha: true,
+            srgb: false,
+        },
+        b"DXT2" | b"DXT3" => CompressedFormat::Bc2 { srgb: false },
+        b"DXT4" | b"DXT5" => CompressedFormat::Bc3 { srgb: false },
+        b"ATI1" | b"BC4U" => CompressedFormat::Bc4,
+        b"ATI2" | b"BC5U" => CompressedFormat::Bc5,
+        _ => return Err(CompressedTextureError::UnknownFormat(four_cc)),
+    };
+
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BC1_RGB_VK: u32 = 131;
+
+    fn dds(width: u32, height: u32, mip_map_count: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0; 128];
+        bytes[..4].copy_from_slice(DDS_MAGIC);
+        bytes[4..8].copy_from_slice(&124_u32.to_le_bytes());
+        bytes[8..12].copy_from_slice(&DDSD_MIPMAPCOUNT.to_le_bytes());
+        bytes[12..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&width.to_le_bytes());
+        bytes[28..32].copy_from_slice(&mip_map_count.to_le_bytes());
+        bytes[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        bytes[84..88].copy_from_slice(b"DXT1");
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    /// A KTX2 file whose levels are stored back to back after the level index.
+    fn ktx2(width: u32, height: u32, level_count: u32, levels: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = vec![0; 80];
+        bytes[..12].copy_from_slice(&KTX2_IDENTIFIER);
+        bytes[12..16].copy_from_slice(&BC1_RGB_VK.to_le_bytes());
+        bytes[20..24].copy_from_slice(&width.to_le_bytes());
+        bytes[24..28].copy_from_slice(&height.to_le_bytes());
+        bytes[40..44].copy_from_slice(&level_count.to_le_bytes());
+
+        let mut offset = 80 + levels.len() * 24;
+        for level in levels {
+            bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(level.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&(level.len() as u64).to_le_bytes());
+            offset += level.len();
+        }
+        for level in levels {
+            bytes.extend_from_slice(level);
+        }
+        bytes
+    }
+
+    fn invalid(result: Result<CompressedImage, CompressedTextureError>) -> &'static str {
+        match result {
+            Err(CompressedTextureError::InvalidContainer(reason)) => reason,
+            other => panic!("expected an invalid container, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn dds_mip_chain() {
+        let image = CompressedImage::from_dds(&dds(8, 4, 4, &[7; 40])).unwrap();
+        assert_eq!(
+            image.format,
+            CompressedFormat::Bc1 {
+                alpha: true,
+                srgb: false
+            }
+        );
+        assert_eq!((image.width, image.height), (8, 4));
+        assert_eq!(
+            image.levels,
+            vec![vec![7; 16], vec![7; 8], vec![7; 8], vec![7; 8]]
+        );
+    }
+
+    #[test]
+    fn dds_too_many_levels() {
+        let file = dds(4, 4, 40, &[0; 8 * 40]);
+        assert_eq!(
+            invalid(CompressedImage::from_dds(&file)),
+            "too many mip levels"
+        );
+    }
+
+    #[test]
+    fn dds_truncated() {
+        let file = dds(8, 8, 1, &[0; 16]);
+        assert_eq!(
+            invalid(CompressedImage::from_dds(&file)),
+            "unexpected end of file"
+        );
+        assert_eq!(
+            invalid(CompressedImage::from_dds(&file[..100])),
+            "unexpected end of file"
+        );
+    }
+
+    #[test]
+    fn dds_empty() {
+        assert_eq!(
+            invalid(CompressedImage::from_dds(&dds(0, 4, 1, &[]))),
+            "empty image"
+        );
+    }
+
+    #[test]
+    fn dds_unknown_four_cc() {
+        let mut file = dds(4, 4, 1, &[0; 8]);
+        file[84..88].copy_from_slice(b"ABCD");
+        assert!(matches!(
+            CompressedImage::from_dds(&file),
+            Err(CompressedTextureError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn ktx2_mip_chain() {
+        let file = ktx2(4, 4, 3, &[&[1; 8], &[2; 8], &[3; 8]]);
+        let image = CompressedImage::from_ktx2(&file).unwrap();
+        assert_eq!(
+            image.format,
+            CompressedFormat::Bc1 {
+                alpha: false,
+                srgb: false
+            }
+        );
+        assert_eq!(image.levels, vec![vec![1; 8], vec![2; 8], vec![3; 8]]);
+    }
+
+    #[test]
+    fn ktx2_too_many_levels() {
+        let file = ktx2(4, 4, 40, &[&[0; 8]]);
+        assert_eq!(
+            invalid(CompressedImage::from_ktx2(&file)),
+            "too many mip levels"
+        );
+    }
+
+    #[test]
+    fn ktx2_level_too_small() {
+        let file = ktx2(8, 8, 1, &[&[0; 8]]);
+        assert_eq!(
+            invalid(CompressedImage::from_ktx2(&file)),
+            "mip level is too small"
+        );
+    }
+
+    #[test]
+    fn ktx2_supercompressed() {
+        let mut file = ktx2(4, 4, 1, &[&[0; 8]]);
+        file[44..48].copy_from_slice(&2_u32.to_le_bytes());
+        assert!(matches!(
+            CompressedImage::from_ktx2(&file),
+            Err(CompressedTextureError::Supercompressed(2))
+        ));
+    }
+
+    #[test]
+    fn level_limits() {
+        assert!(check_levels(1, 1, 1).is_ok());
+        assert!(check_levels(1, 1, 2).is_err());
+        assert!(check_levels(256, 1, 9).is_ok());
+        assert!(check_levels(256, 1, 10).is_err());
+        assert!(check_levels(u32::MAX, 1, 32).is_ok());
+        assert!(check_levels(u32::MAX, 1, 33).is_err());
+        assert!(check_levels(4, 4, 0).is_err());
+    }
+}
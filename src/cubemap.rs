@@ -0,0 +1,390 @@
+use crate::{SamplerDesc, TextureError, TextureFormat, TextureObject, TextureUnits, mip_levels};
+use std::{f32::consts::PI, ffi::c_void, fmt::Debug, ptr};
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX = gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+    NegativeX = gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+    PositiveY = gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+    NegativeY = gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+    PositiveZ = gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+    NegativeZ = gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+}
+
+impl CubeFace {
+    /// Every face, in the order GL numbers them.
+    pub const ALL: [Self; 6] = [
+        Self::PositiveX,
+        Self::NegativeX,
+        Self::PositiveY,
+        Self::NegativeY,
+        Self::PositiveZ,
+        Self::NegativeZ,
+    ];
+
+    /// The direction through the texel at `s`, `t` in `-1.0..=1.0`,
+    /// from the cube map table of the GL spec.
+    fn direction(self, s: f32, t: f32) -> [f32; 3] {
+        match self {
+            Self::PositiveX => [1.0, -t, -s],
+            Self::NegativeX => [-1.0, -t, s],
+            Self::PositiveY => [s, 1.0, t],
+            Self::NegativeY => [s, -1.0, -t],
+            Self::PositiveZ => [s, -t, 1.0],
+            Self::NegativeZ => [-s, -t, -1.0],
+        }
+    }
+}
+
+/// A cube map, sampled with a direction through a `samplerCube`.
+///
+/// Creating one enables seamless filtering across the faces for the whole context,
+/// see [`CubemapTexture::set_seamless`].
+pub struct CubemapTexture {
+    id: u32,
+    size: i32,
+    format: TextureFormat,
+    levels: i32,
+}
+
+impl CubemapTexture {
+    /// Create a cube map from six square faces in the order of [`CubeFace::ALL`].
+    ///
+    /// Face rows are top row first, the way image files store them, which is
+    /// what the cube map convention of GL expects.
+    pub fn from_faces(
+        faces: [&[u8]; 6],
+        size: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Self::create(Some(faces), size, format)
+    }
+
+    /// Create a cube map with undefined contents, e.g. for rendering into.
+    pub fn empty(size: i32, format: TextureFormat) -> Result<Self, TextureError> {
+        Self::create(None, size, format)
+    }
+
+    /// Create a cube map by projecting an equirectangular panorama onto faces
+    /// `face_size` pixels wide. The panorama is top row first, with `-Z` in the middle.
+    ///
+    /// The projection runs on the CPU with bilinear filtering, and only supports
+    /// formats with `u8` or `f32` components.
+    pub fn from_equirectangular(
+        data: &[u8],
+        width: i32,
+        height: i32,
+        format: TextureFormat,
+        face_size: i32,
+    ) -> Result<Self, TextureError> {
+        if width <= 0 || height <= 0 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+        if face_size <= 0 {
+            return Err(TextureError::InvalidSize {
+                width: face_size,
+                height: face_size,
+            });
+        }
+
+        let expected = width as usize * height as usize * format.bytes_per_pixel();
+        if data.len() != expected {
+            return Err(TextureError::DataSizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        let panorama = Panorama {
+            width: width as usize,
+            height: height as usize,
+            channels: format.channels(),
+        };
+        let faces = match format.pixel_type() {
+            gl::UNSIGNED_BYTE => CubeFace::ALL.map(|face| {
+                panorama.project(
+                    face,
+                    face_size as usize,
+                    data,
+                    |c| c as f32,
+                    |c| c.round().clamp(0.0, 255.0) as u8,
+                )
+            }),
+            gl::FLOAT => {
+                let floats: Vec<f32> = data
+                    .chunks_exact(4)
+                    .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect();
+                CubeFace::ALL.map(|face| {
+                    panorama
+                        .project(face, face_size as usize, &floats, |c| c, |c| c)
+                        .into_iter()
+                        .flat_map(f32::to_ne_bytes)
+                        .collect()
+                })
+            }
+            _ => return Err(TextureError::UnsupportedFormat(format)),
+        };
+
+        Self::from_faces(faces.each_ref().map(Vec::as_slice), face_size, format)
+    }
+
+    fn create(
+        faces: Option<[&[u8]; 6]>,
+        size: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        if size <= 0 {
+            return Err(TextureError::InvalidSize {
+                width: size,
+                height: size,
+            });
+        }
+
+        let expected = size as usize * size as usize * format.bytes_per_pixel();
+        for face in faces.iter().flatten() {
+            if face.len() != expected {
+                return Err(TextureError::DataSizeMismatch {
+                    expected,
+                    actual: face.len(),
+                });
+            }
+        }
+
+        let levels = if format.is_filterable() {
+            mip_levels(size, size)
+        } else {
+            1
+        };
+
+        let mut id = 0_u32;
+        unsafe {
+            gl::GenTextures(1, ptr::addr_of_mut!(id));
+            TextureUnits::bind_for_update(gl::TEXTURE_CUBE_MAP, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            let storage = gl::TexStorage2D::is_loaded();
+            if storage {
+                gl::TexStorage2D(gl::TEXTURE_CUBE_MAP, levels, format as u32, size, size);
+            }
+
+            for (index, face) in CubeFace::ALL.into_iter().enumerate() {
+                let data = faces.map_or(ptr::null(), |f| f[index].as_ptr() as *const c_void);
+
+                if !storage {
+                    gl::TexImage2D(
+                        face as u32,
+                        0,
+                        format as i32,
+                        size,
+                        size,
+                        0,
+                        format.pixel_format(),
+                        format.pixel_type(),
+                        data,
+                    );
+                } else if faces.is_some() {
+                    gl::TexSubImage2D(
+                        face as u32,
+                        0,
+                        0,
+                        0,
+                        size,
+                        size,
+                        format.pixel_format(),
+                        format.pixel_type(),
+                        data,
+                    );
+                }
+            }
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            if levels > 1 {
+                gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            }
+        }
+
+        let cubemap = Self {
+            id,
+            size,
+            format,
+            levels,
+        };
+
+        // directions near the edges would pick up the border otherwise
+        let desc = SamplerDesc::linear().wrap(crate::Wrap::ClampToEdge);
+        cubemap.set_sampler(&if levels == 1 {
+            desc.filter(crate::Filter::Nearest).mipmap_filter(None)
+        } else {
+            desc
+        });
+        Self::set_seamless(true);
+
+        Ok(cubemap)
+    }
+
+    /// Filter across the edges of the faces, for every cube map of the context.
+    pub fn set_seamless(enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            } else {
+                gl::Disable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            }
+        }
+    }
+
+    /// Set how the cube map is sampled. Binds it to the active texture unit.
+    pub fn set_sampler(&self, desc: &SamplerDesc) {
+        desc.apply_to_texture(gl::TEXTURE_CUBE_MAP, self.id);
+    }
+
+    /// The width and height of every face.
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn levels(&self) -> i32 {
+        self.levels
+    }
+}
+
+impl TextureObject for CubemapTexture {
+    fn raw_id(&self) -> u32 {
+        self.id
+    }
+
+    fn target(&self) -> u32 {
+        gl::TEXTURE_CUBE_MAP
+    }
+}
+
+impl Drop for CubemapTexture {
+    fn drop(&mut self) {
+        TextureUnits::forget_texture(self.id);
+        unsafe { gl::DeleteTextures(1, ptr::addr_of!(self.id)) }
+    }
+}
+
+impl Debug for CubemapTexture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CubemapTexture")
+            .field("id", &self.id)
+            .field("size", &self.size)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+struct Panorama {
+    width: usize,
+    height: usize,
+    channels: usize,
+}
+
+impl Panorama {
+    /// Resample the panorama onto one face, converting components through `f32`.
+    fn project<T: Copy>(
+        &self,
+        face: CubeFace,
+        size: usize,
+        data: &[T],
+        to_f32: impl Fn(T) -> f32,
+        from_f32: impl Fn(f32) -> T,
+    ) -> Vec<T> {
+        let mut out = Vec::with_capacity(size * size * self.channels);
+        let texel =
+            |x: usize, y: usize, c: usize| to_f32(data[(y * self.width + x) * self.channels + c]);
+
+        for row in 0..size {
+            for column in 0..size {
+                let s = (column as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let t = (row as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let [x, y, z] = face.direction(s, t);
+
+                let longitude = x.atan2(-z);
+                let latitude = (y / (x * x + y * y + z * z).sqrt()).asin();
+                let u = (0.5 + longitude / (2.0 * PI)) * self.width as f32 - 0.5;
+                let v = (0.5 - latitude / PI) * self.height as f32 - 0.5;
+
+                // wrap around horizontally, clamp at the poles
+                let u0 = u.floor();
+                let v0 = v.floor().clamp(0.0, self.height as f32 - 1.0);
+                let (fu, fv) = (u - u0, (v - v0).clamp(0.0, 1.0));
+                let x0 = (u0 as isize).rem_euclid(self.width as isize) as usize;
+                let x1 = (x0 + 1) % self.width;
+                let y0 = v0 as usize;
+                let y1 = (y0 + 1).min(self.height - 1);
+
+                for c in 0..self.channels {
+                    let top = texel(x0, y0, c) * (1.0 - fu) + texel(x1, y0, c) * fu;
+                    let bottom = texel(x0, y1, c) * (1.0 - fu) + texel(x1, y1, c) * fu;
+                    out.push(from_f32(top * (1.0 - fv) + bottom * fv));
+                }
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PANORAMA: Panorama = Panorama {
+        width: 8,
+        height: 4,
+        channels: 2,
+    };
+
+    /// A panorama whose texels hold their own column and row.
+    fn coordinates() -> Vec<f32> {
+        (0..4)
+            .flat_map(|row| (0..8).flat_map(move |column| [column as f32, row as f32]))
+            .collect()
+    }
+
+    /// The panorama coordinates the centre texel of a 1x1 face samples.
+    fn centre(face: CubeFace) -> [f32; 2] {
+        let out = PANORAMA.project(face, 1, &coordinates(), |c| c, |c| c);
+        [out[0], out[1]]
+    }
+
+    #[test]
+    fn face_centres() {
+        // -Z looks at the middle of the panorama, +X a quarter to the right
+        assert_eq!(centre(CubeFace::NegativeZ), [3.5, 1.5]);
+        assert_eq!(centre(CubeFace::PositiveX), [5.5, 1.5]);
+        assert_eq!(centre(CubeFace::NegativeX), [1.5, 1.5]);
+        assert_eq!(centre(CubeFace::PositiveY)[1], 0.0);
+        assert_eq!(centre(CubeFace::NegativeY)[1], 3.0);
+    }
+
+    #[test]
+    fn wraps_around_horizontally() {
+        // +Z looks at the seam, between the last and the first column
+        let data: Vec<f32> = (0..4)
+            .flat_map(|_| {
+                (0..8).flat_map(|column| [[3.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0][column]; 2])
+            })
+            .collect();
+        let out = PANORAMA.project(CubeFace::PositiveZ, 1, &data, |c| c, |c| c);
+        assert_eq!(out[0], 2.0);
+    }
+
+    #[test]
+    fn face_orientation() {
+        // on the -Z face, s points to -X and t to -Y, so columns go left and rows go down
+        let out = PANORAMA.project(CubeFace::NegativeZ, 2, &coordinates(), |c| c, |c| c);
+        let texel = |x: usize, y: usize| [out[(y * 2 + x) * 2], out[(y * 2 + x) * 2 + 1]];
+        assert!(texel(1, 0)[0] < 3.5 && texel(0, 0)[0] > 3.5);
+        assert!(texel(0, 1)[1] > 1.5 && texel(0, 0)[1] < 1.5);
+    }
+}
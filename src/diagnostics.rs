@@ -0,0 +1,259 @@
+use core::fmt;
+
+/// A named piece of shader source, used to point diagnostics back at the original code.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+impl SourceFile {
+    /// A source that was passed in directly instead of being read from a file.
+    pub fn inline(source: &str) -> Self {
+        Self {
+            name: "<source>".to_owned(),
+            source: source.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+        })
+    }
+}
+
+/// A single message from a shader compiler or linker log.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// The name of the file, if the source string number could be mapped to one.
+    pub file: Option<String>,
+    /// The source string number reported by the driver, see `#line`.
+    pub source_string: Option<u32>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A complete info log, parsed into diagnostics.
+///
+/// Understands the Mesa (`0:12(5): error: ...`), NVIDIA (`0(12) : error C0000: ...`)
+/// and AMD/Apple (`ERROR: 0:12: ...`) formats. Lines that match none of them are kept
+/// as diagnostics without a location.
+#[derive(Debug, Clone)]
+pub struct ShaderLog {
+    pub raw: String,
+    pub diagnostics: Vec<Diagnostic>,
+    files: Vec<SourceFile>,
+}
+
+impl ShaderLog {
+    /// Parse `raw`, resolving source string numbers to names and lines in `files`.
+    pub fn parse(raw: impl Into<String>, files: &[SourceFile]) -> Self {
+        let raw = raw.into();
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+
+        for line in raw.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(mut diagnostic) = parse_mesa(line)
+                .or_else(|| parse_nvidia(line))
+                .or_else(|| parse_amd(line))
+            {
+                diagnostic.file = diagnostic
+                    .source_string
+                    .and_then(|s| files.get(s as usize))
+                    .map(|f| f.name.clone());
+                diagnostics.push(diagnostic);
+            } else if let (true, Some(last)) = (
+                line.starts_with(char::is_whitespace),
+                diagnostics.last_mut(),
+            ) {
+                last.message.push('\n');
+                last.message.push_str(line.trim());
+            } else {
+                let (severity, message) = split_severity(line);
+                diagnostics.push(Diagnostic {
+                    file: None,
+                    source_string: None,
+                    line: None,
+                    column: None,
+                    severity: severity.unwrap_or(Severity::Error),
+                    message: message.to_owned(),
+                });
+            }
+        }
+
+        Self {
+            raw,
+            diagnostics,
+            files: files.to_vec(),
+        }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+
+    fn source_line(&self, diagnostic: &Diagnostic) -> Option<&str> {
+        let file = self.files.get(diagnostic.source_string? as usize)?;
+        let line = diagnostic.line?.checked_sub(1)?;
+        file.source.lines().nth(line as usize)
+    }
+}
+
+impl fmt::Display for ShaderLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.diagnostics.is_empty() {
+            return f.write_str(self.raw.trim());
+        }
+
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {}", diagnostic.severity, diagnostic.message)?;
+
+            let Some(line) = diagnostic.line else {
+                continue;
+            };
+            let file = match (&diagnostic.file, diagnostic.source_string) {
+                (Some(name), _) => name.clone(),
+                (None, Some(string)) => string.to_string(),
+                (None, None) => "<unknown>".to_owned(),
+            };
+            let gutter = " ".repeat(line.to_string().len());
+
+            write!(f, "\n{gutter}--> {file}:{line}")?;
+            if let Some(column) = diagnostic.column {
+                write!(f, ":{column}")?;
+            }
+
+            let Some(source) = self.source_line(diagnostic) else {
+                continue;
+            };
+            write!(f, "\n{gutter} |\n{line} | {source}\n{gutter} | ")?;
+
+            match diagnostic.column {
+                Some(column) if column > 0 => {
+                    write!(f, "{}^", " ".repeat(column as usize - 1))?;
+                }
+                // no column, underline the whole line
+                _ => {
+                    let indent = source.len() - source.trim_start().len();
+                    let width = source.trim().len().max(1);
+                    write!(f, "{}{}", " ".repeat(indent), "^".repeat(width))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `0:12(5): error: message`
+fn parse_mesa(line: &str) -> Option<Diagnostic> {
+    let (string, rest) = split_number(line)?;
+    let (line_number, rest) = split_number(rest.strip_prefix(':')?)?;
+    let (column, rest) = split_number(rest.strip_prefix('(')?)?;
+    let (severity, message) = split_severity(rest.strip_prefix("):")?.trim_start());
+
+    Some(Diagnostic {
+        file: None,
+        source_string: Some(string),
+        line: Some(line_number),
+        column: Some(column),
+        severity: severity.unwrap_or(Severity::Error),
+        message: message.to_owned(),
+    })
+}
+
+/// `0(12) : error C0000: message`
+fn parse_nvidia(line: &str) -> Option<Diagnostic> {
+    let (string, rest) = split_number(line)?;
+    let (line_number, rest) = split_number(rest.strip_prefix('(')?)?;
+    let rest = rest.strip_prefix(')')?.trim_start().strip_prefix(':')?;
+    let (severity, message) = split_severity(rest.trim_start());
+
+    Some(Diagnostic {
+        file: None,
+        source_string: Some(string),
+        line: Some(line_number),
+        column: None,
+        severity: severity?,
+        message: message.to_owned(),
+    })
+}
+
+/// `ERROR: 0:12: message`
+fn parse_amd(line: &str) -> Option<Diagnostic> {
+    let (severity, rest) = split_severity(line);
+    let (string, rest) = split_number(rest)?;
+    let (line_number, rest) = split_number(rest.strip_prefix(':')?)?;
+    let message = rest.strip_prefix(':')?.trim();
+
+    Some(Diagnostic {
+        file: None,
+        source_string: Some(string),
+        line: Some(line_number),
+        column: None,
+        severity: severity?,
+        message: message.to_owned(),
+    })
+}
+
+fn split_number(s: &str) -> Option<(u32, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let number = s[..end].parse().ok()?;
+    Some((number, &s[end..]))
+}
+
+/// Split a leading `error:`/`warning:`/`info:` (in any case, optionally followed by
+/// an NVIDIA style error code) off a message.
+fn split_severity(s: &str) -> (Option<Severity>, &str) {
+    const PREFIXES: [(&str, Severity); 4] = [
+        ("error", Severity::Error),
+        ("warning", Severity::Warning),
+        ("info", Severity::Note),
+        ("note", Severity::Note),
+    ];
+
+    for (prefix, severity) in PREFIXES {
+        let Some(head) = s.get(..prefix.len()) else {
+            continue;
+        };
+        if !head.eq_ignore_ascii_case(prefix) {
+            continue;
+        }
+
+        let rest = &s[prefix.len()..];
+        // `error:` or `error C1008:`
+        if let Some(rest) = rest.strip_prefix(':') {
+            return (Some(severity), rest.trim_start());
+        }
+        if let Some((code, _)) = rest.strip_prefix(' ').and_then(|r| r.split_once(':'))
+            && !code.is_empty()
+            && !code.contains(char::is_whitespace)
+        {
+            return (Some(severity), rest.trim_start());
+        }
+    }
+
+    (None, s)
+}
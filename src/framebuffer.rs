@@ -0,0 +1,685 @@
+use crate::{FormatKind, Program, Renderbuffer, Texture, Texture2DMultisample, TextureFormat};
+use core::{error, fmt};
+use std::{marker::PhantomData, ptr};
+
+/// Where an image is attached to a [`Framebuffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentPoint {
+    /// `GL_COLOR_ATTACHMENTi`, written by fragment output `i`.
+    Color(u32),
+    Depth,
+    DepthStencil,
+}
+
+impl AttachmentPoint {
+    const fn gl_attachment(self) -> u32 {
+        match self {
+            Self::Color(index) => gl::COLOR_ATTACHMENT0 + index,
+            Self::Depth => gl::DEPTH_ATTACHMENT,
+            Self::DepthStencil => gl::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+
+    const fn accepts(self, format: TextureFormat) -> bool {
+        matches!(
+            (self, format.kind()),
+            (
+                Self::Color(_),
+                FormatKind::Float | FormatKind::SignedInteger | FormatKind::UnsignedInteger
+            ) | (Self::Depth, FormatKind::Depth)
+                | (Self::DepthStencil, FormatKind::DepthStencil)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FramebufferError {
+    /// The index is not below `GL_MAX_COLOR_ATTACHMENTS`.
+    TooManyColorAttachments { index: u32, max: u32 },
+    /// More fragment outputs than `GL_MAX_DRAW_BUFFERS` were routed.
+    TooManyDrawBuffers { count: usize, max: usize },
+    /// Nothing is attached to this color attachment.
+    MissingColorAttachment(u32),
+    /// Two fragment outputs were routed to the same color attachment.
+    DuplicateDrawBuffer(u32),
+    /// The color attachment isn't written by any fragment output, so it can't be cleared.
+    NotDrawn(u32),
+    /// The clear value doesn't match the format, e.g. floats for an integer format.
+    ClearTypeMismatch { index: u32, format: TextureFormat },
+    /// The program has no active fragment output of this name.
+    UnknownOutput(String),
+    /// The format can't be attached there, e.g. a depth format as a color attachment.
+    InvalidAttachmentFormat {
+        point: AttachmentPoint,
+        format: TextureFormat,
+    },
+    /// `GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT`
+    IncompleteAttachment,
+    /// `GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT`
+    MissingAttachment,
+    /// `GL_FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER`
+    IncompleteDrawBuffer,
+    /// `GL_FRAMEBUFFER_INCOMPLETE_READ_BUFFER`
+    IncompleteReadBuffer,
+    /// `GL_FRAMEBUFFER_UNSUPPORTED`
+    Unsupported,
+    /// `GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE`
+    SampleCountMismatch,
+    /// `GL_FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS`
+    LayerMismatch,
+    /// Any other status `glCheckFramebufferStatus` returned.
+    Incomplete(u32),
+}
+
+impl fmt::Display for FramebufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyColorAttachments { index, max } => write!(
+                f,
+                "Color attachment {index} is out of range, the context supports {max}"
+            ),
+            Self::InvalidAttachmentFormat { point, format } => {
+                write!(f, "A {format:?} image can't be attached to {point:?}")
+            }
+            Self::TooManyDrawBuffers { count, max } => write!(
+                f,
+                "{count} draw buffers were given, the context supports {max}"
+            ),
+            Self::MissingColorAttachment(index) => {
+                write!(f, "Nothing is attached to color attachment {index}")
+            }
+            Self::DuplicateDrawBuffer(index) => write!(
+                f,
+                "Color attachment {index} is written by more than one fragment output"
+            ),
+            Self::NotDrawn(index) => write!(
+                f,
+                "Color attachment {index} isn't a draw buffer, so it can't be cleared"
+            ),
+            Self::ClearTypeMismatch { index, format } => write!(
+                f,
+                "Color attachment {index} is {format:?}, the clear value has another type"
+            ),
+            Self::UnknownOutput(name) => {
+                write!(f, "The program has no fragment output called {name}")
+            }
+            Self::IncompleteAttachment => write!(
+                f,
+                "An attachment is incomplete, it may have no storage or a size of 0"
+            ),
+            Self::MissingAttachment => write!(f, "The framebuffer has no attachments"),
+            Self::IncompleteDrawBuffer => {
+                write!(f, "A draw buffer points to a missing color attachment")
+            }
+            Self::IncompleteReadBuffer => {
+                write!(f, "The read buffer points to a missing color attachment")
+            }
+            Self::Unsupported => write!(
+                f,
+                "The driver doesn't support this combination of attachment formats"
+            ),
+            Self::SampleCountMismatch => write!(
+                f,
+                "The attachments don't have the same number of samples or sample locations"
+            ),
+            Self::LayerMismatch => write!(
+                f,
+                "Layered and non layered attachments, or different texture targets, are mixed"
+            ),
+            Self::Incomplete(status) => {
+                write!(f, "The framebuffer is incomplete, status {status:#x}")
+            }
+        }
+    }
+}
+
+impl error::Error for FramebufferError {}
+
+/// The value a color attachment is cleared to, its type has to match the format:
+/// floats for normalized and float formats, signed or unsigned integers for integer ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClearColor {
+    Float([f32; 4]),
+    Int([i32; 4]),
+    Uint([u32; 4]),
+}
+
+impl ClearColor {
+    const fn accepts(self, format: TextureFormat) -> bool {
+        matches!(
+            (self, format.kind()),
+            (Self::Float(_), FormatKind::Float)
+                | (Self::Int(_), FormatKind::SignedInteger)
+                | (Self::Uint(_), FormatKind::UnsignedInteger)
+        )
+    }
+}
+
+impl From<[f32; 4]> for ClearColor {
+    fn from(value: [f32; 4]) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<[i32; 4]> for ClearColor {
+    fn from(value: [i32; 4]) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<[u32; 4]> for ClearColor {
+    fn from(value: [u32; 4]) -> Self {
+        Self::Uint(value)
+    }
+}
+
+mod private {
+    pub trait Sealed {
+        /// Attach to the framebuffer bound to `GL_DRAW_FRAMEBUFFER`.
+        fn attach(&self, attachment: u32);
+    }
+}
+
+/// An image a [`Framebuffer`] can render to.
+pub trait FramebufferAttachment: private::Sealed {
+    fn attachment_size(&self) -> (i32, i32);
+
+    fn attachment_format(&self) -> TextureFormat;
+}
+
+macro_rules! framebuffer_attachment {
+    ($($ty:ty => |$self:ident, $attachment:ident| $attach:expr),* $(,)?) => {$(
+        impl private::Sealed for $ty {
+            fn attach(&$self, $attachment: u32) {
+                unsafe { $attach }
+            }
+        }
+
+        impl FramebufferAttachment for $ty {
+            fn attachment_size(&self) -> (i32, i32) {
+                (self.width(), self.height())
+            }
+
+            fn attachment_format(&self) -> TextureFormat {
+                self.format()
+            }
+        }
+    )*};
+}
+
+framebuffer_attachment! {
+    Texture => |self, attachment| gl::FramebufferTexture2D(
+        gl::DRAW_FRAMEBUFFER,
+        attachment,
+        gl::TEXTURE_2D,
+        self.id,
+        0,
+    ),
+    Texture2DMultisample => |self, attachment| gl::FramebufferTexture2D(
+        gl::DRAW_FRAMEBUFFER,
+        attachment,
+        gl::TEXTURE_2D_MULTISAMPLE,
+        crate::TextureObject::raw_id(self),
+        0,
+    ),
+    Renderbuffer => |self, attachment| gl::FramebufferRenderbuffer(
+        gl::DRAW_FRAMEBUFFER,
+        attachment,
+        gl::RENDERBUFFER,
+        self.id,
+    ),
+}
+
+/// A render target made of textures and renderbuffers.
+///
+/// ```ignore
+/// let color = Texture::with_mipmaps(None, 512, 512, TextureFormat::Rgba8, Mipmaps::None)?;
+/// let depth = Renderbuffer::new(512, 512, TextureFormat::Depth24, 0)?;
+///
+/// let mut framebuffer = Framebuffer::new();
+/// framebuffer.attach_color(0, &color)?;
+/// framebuffer.attach_depth(&depth)?;
+/// framebuffer.check()?;
+///
+/// {
+///     let _target = framebuffer.bind_scoped();
+///     // draw the scene into `color`
+/// }
+/// ```
+///
+/// Several color attachments make a G-buffer, each fragment output is written to
+/// the attachment of the same index unless routed elsewhere:
+///
+/// ```ignore
+/// let mut gbuffer = Framebuffer::new();
+/// gbuffer.attach_color(0, &albedo)?;
+/// gbuffer.attach_color(1, &normals)?;
+/// gbuffer.attach_depth(&depth)?;
+/// gbuffer.route_outputs(&program, &[("albedo", 0), ("normal", 1)])?;
+/// gbuffer.check()?;
+///
+/// let _target = gbuffer.bind_scoped();
+/// gbuffer.clear_color(0, [0.0, 0.0, 0.0, 1.0])?;
+/// gbuffer.clear_color(1, [0.5, 0.5, 1.0, 0.0])?;
+/// gbuffer.clear_depth(1.0);
+/// ```
+///
+/// Attached images are borrowed for the lifetime of the framebuffer. GL only detaches
+/// a deleted image from the bound framebuffer, any other would keep rendering into it.
+#[derive(Debug)]
+pub struct Framebuffer<'a> {
+    id: u32,
+    /// The color attachments, ordered by index.
+    colors: Vec<ColorAttachment>,
+    /// The color attachment written by each fragment output, set with
+    /// [`Framebuffer::set_draw_buffers`]. `None` routes output `i` to attachment `i`.
+    draw_buffers: Option<Vec<Option<u32>>>,
+    /// The sizes of the depth and stencil images. A depth stencil image sets both.
+    depth: Option<(i32, i32)>,
+    stencil: Option<(i32, i32)>,
+    attachments: PhantomData<&'a ()>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ColorAttachment {
+    index: u32,
+    format: TextureFormat,
+    size: (i32, i32),
+}
+
+impl<'a> Framebuffer<'a> {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe { gl::GenFramebuffers(1, ptr::addr_of_mut!(id)) };
+
+        let framebuffer = Self {
+            id,
+            colors: vec![],
+            draw_buffers: None,
+            depth: None,
+            stencil: None,
+            attachments: PhantomData,
+        };
+        framebuffer.update_draw_buffers();
+        framebuffer
+    }
+
+    /// `GL_MAX_COLOR_ATTACHMENTS` of the current context.
+    pub fn max_color_attachments() -> u32 {
+        let mut max = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, ptr::addr_of_mut!(max)) };
+        max as u32
+    }
+
+    /// `GL_MAX_DRAW_BUFFERS` of the current context, how many fragment outputs can be written.
+    pub fn max_draw_buffers() -> usize {
+        let mut max = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_DRAW_BUFFERS, ptr::addr_of_mut!(max)) };
+        max as usize
+    }
+
+    /// Attach an image of a color format to `GL_COLOR_ATTACHMENT0 + index`.
+    pub fn attach_color(
+        &mut self,
+        index: u32,
+        image: &'a impl FramebufferAttachment,
+    ) -> Result<(), FramebufferError> {
+        self.attach_color_untracked(index, image)
+    }
+
+    /// [`Framebuffer::attach_color`] for an image the caller keeps alive as long as the
+    /// framebuffer, e.g. one owned next to it.
+    pub(crate) fn attach_color_untracked(
+        &mut self,
+        index: u32,
+        image: &impl FramebufferAttachment,
+    ) -> Result<(), FramebufferError> {
+        let max = Self::max_color_attachments();
+        if index >= max {
+            return Err(FramebufferError::TooManyColorAttachments { index, max });
+        }
+
+        self.attach(AttachmentPoint::Color(index), image)?;
+        let attachment = ColorAttachment {
+            index,
+            format: image.attachment_format(),
+            size: image.attachment_size(),
+        };
+        match self.color_position(index) {
+            Ok(position) => self.colors[position] = attachment,
+            Err(position) => self.colors.insert(position, attachment),
+        }
+        self.update_draw_buffers();
+
+        Ok(())
+    }
+
+    /// Attach an image of a depth format.
+    pub fn attach_depth(
+        &mut self,
+        image: &'a impl FramebufferAttachment,
+    ) -> Result<(), FramebufferError> {
+        self.attach(AttachmentPoint::Depth, image)?;
+        self.depth = Some(image.attachment_size());
+        Ok(())
+    }
+
+    /// Attach an image of a depth and stencil format, used for both tests.
+    pub fn attach_depth_stencil(
+        &mut self,
+        image: &'a impl FramebufferAttachment,
+    ) -> Result<(), FramebufferError> {
+        self.attach_depth_stencil_untracked(image)
+    }
+
+    /// [`Framebuffer::attach_depth_stencil`] for an image the caller keeps alive,
+    /// see [`Framebuffer::attach_color_untracked`].
+    pub(crate) fn attach_depth_stencil_untracked(
+        &mut self,
+        image: &impl FramebufferAttachment,
+    ) -> Result<(), FramebufferError> {
+        self.attach(AttachmentPoint::DepthStencil, image)?;
+        self.depth = Some(image.attachment_size());
+        self.stencil = Some(image.attachment_size());
+        Ok(())
+    }
+
+    fn attach(
+        &mut self,
+        point: AttachmentPoint,
+        image: &impl FramebufferAttachment,
+    ) -> Result<(), FramebufferError> {
+        let format = image.attachment_format();
+        if !point.accepts(format) {
+            return Err(FramebufferError::InvalidAttachmentFormat { point, format });
+        }
+
+        self.with_bound(|| image.attach(point.gl_attachment()));
+        Ok(())
+    }
+
+    /// Choose the color attachment every fragment output writes to, output `i` goes to
+    /// `attachments[i]` and is discarded for `None`. Outputs past the end are discarded too.
+    pub fn set_draw_buffers(
+        &mut self,
+        attachments: &[Option<u32>],
+    ) -> Result<(), FramebufferError> {
+        let max = Self::max_draw_buffers();
+        if attachments.len() > max {
+            return Err(FramebufferError::TooManyDrawBuffers {
+                count: attachments.len(),
+                max,
+            });
+        }
+
+        for (output, index) in attachments.iter().enumerate() {
+            let Some(index) = *index else { continue };
+            if self.color_format(index).is_none() {
+                return Err(FramebufferError::MissingColorAttachment(index));
+            }
+            if attachments[..output].contains(&Some(index)) {
+                return Err(FramebufferError::DuplicateDrawBuffer(index));
+            }
+        }
+
+        self.draw_buffers = Some(attachments.to_vec());
+        self.update_draw_buffers();
+        Ok(())
+    }
+
+    /// Route fragment output `i` to color attachment `i` again.
+    pub fn reset_draw_buffers(&mut self) {
+        self.draw_buffers = None;
+        self.update_draw_buffers();
+    }
+
+    /// Route the fragment outputs of `program` to color attachments by name, outputs
+    /// that aren't listed are discarded.
+    pub fn route_outputs(
+        &mut self,
+        program: &Program,
+        outputs: &[(&str, u32)],
+    ) -> Result<(), FramebufferError> {
+        let mut attachments = vec![];
+        for &(name, index) in outputs {
+            let location = program
+                .output_location(name)
+                .ok_or_else(|| FramebufferError::UnknownOutput(name.to_owned()))?
+                as usize;
+            if attachments.len() <= location {
+                attachments.resize(location + 1, None);
+            }
+            attachments[location] = Some(index);
+        }
+
+        self.set_draw_buffers(&attachments)
+    }
+
+    /// The color attachment every fragment output writes to.
+    pub fn draw_buffers(&self) -> Vec<Option<u32>> {
+        match &self.draw_buffers {
+            Some(attachments) => attachments.clone(),
+            None => {
+                let count = self.colors.last().map_or(0, |last| last.index + 1);
+                (0..count)
+                    .map(|index| self.color_format(index).map(|_| index))
+                    .collect()
+            }
+        }
+    }
+
+    fn color_position(&self, index: u32) -> Result<usize, usize> {
+        self.colors
+            .binary_search_by_key(&index, |attachment| attachment.index)
+    }
+
+    fn color_format(&self, index: u32) -> Option<TextureFormat> {
+        self.color_position(index)
+            .ok()
+            .map(|position| self.colors[position].format)
+    }
+
+    /// Apply the draw buffers and read from the first color attachment.
+    /// Without color attachments nothing is drawn or read, which depth only
+    /// framebuffers need to be complete.
+    fn update_draw_buffers(&self) {
+        let buffers: Vec<u32> = self
+            .draw_buffers()
+            .into_iter()
+            .map(|index| index.map_or(gl::NONE, |index| gl::COLOR_ATTACHMENT0 + index))
+            .collect();
+        let read = self.colors.first().map_or(gl::NONE, |attachment| {
+            gl::COLOR_ATTACHMENT0 + attachment.index
+        });
+
+        self.with_bound(|| unsafe {
+            gl::DrawBuffers(buffers.len() as i32, buffers.as_ptr());
+            gl::ReadBuffer(read);
+        });
+    }
+
+    /// Clear one color attachment, which has to be a draw buffer. Like [`clear`](crate::clear),
+    /// this respects the scissor test and color mask.
+    pub fn clear_color(
+        &self,
+        index: u32,
+        value: impl Into<ClearColor>,
+    ) -> Result<(), FramebufferError> {
+        let format = self
+            .color_format(index)
+            .ok_or(FramebufferError::MissingColorAttachment(index))?;
+        let value = value.into();
+        if !value.accepts(format) {
+            return Err(FramebufferError::ClearTypeMismatch { index, format });
+        }
+        // glClearBuffer takes the index of the draw buffer, not of the attachment
+        let draw_buffer = self
+            .draw_buffers()
+            .iter()
+            .position(|&attachment| attachment == Some(index))
+            .ok_or(FramebufferError::NotDrawn(index))? as i32;
+
+        self.with_bound(|| unsafe {
+            match value {
+                ClearColor::Float(value) => {
+                    gl::ClearBufferfv(gl::COLOR, draw_buffer, value.as_ptr())
+                }
+                ClearColor::Int(value) => gl::ClearBufferiv(gl::COLOR, draw_buffer, value.as_ptr()),
+                ClearColor::Uint(value) => {
+                    gl::ClearBufferuiv(gl::COLOR, draw_buffer, value.as_ptr())
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Clear the depth attachment, respecting the depth mask.
+    pub fn clear_depth(&self, depth: f32) {
+        self.with_bound(|| unsafe { gl::ClearBufferfv(gl::DEPTH, 0, &depth) });
+    }
+
+    /// Clear the stencil part of the depth stencil attachment, respecting the stencil mask.
+    pub fn clear_stencil(&self, stencil: i32) {
+        self.with_bound(|| unsafe { gl::ClearBufferiv(gl::STENCIL, 0, &stencil) });
+    }
+
+    /// Clear both parts of the depth stencil attachment.
+    pub fn clear_depth_stencil(&self, depth: f32, stencil: i32) {
+        self.with_bound(|| unsafe { gl::ClearBufferfi(gl::DEPTH_STENCIL, 0, depth, stencil) });
+    }
+
+    /// Check that the framebuffer can be rendered to.
+    pub fn check(&self) -> Result<(), FramebufferError> {
+        if self.size().is_none() {
+            // a framebuffer without attachments reports this too, but only after the first bind
+            return Err(FramebufferError::MissingAttachment);
+        }
+
+        let status =
+            self.with_bound(|| unsafe { gl::CheckFramebufferStatus(gl::DRAW_FRAMEBUFFER) });
+
+        match status {
+            gl::FRAMEBUFFER_COMPLETE => Ok(()),
+            gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => Err(FramebufferError::IncompleteAttachment),
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
+                Err(FramebufferError::MissingAttachment)
+            }
+            gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => Err(FramebufferError::IncompleteDrawBuffer),
+            gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => Err(FramebufferError::IncompleteReadBuffer),
+            gl::FRAMEBUFFER_UNSUPPORTED => Err(FramebufferError::Unsupported),
+            gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => Err(FramebufferError::SampleCountMismatch),
+            gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => Err(FramebufferError::LayerMismatch),
+            status => Err(FramebufferError::Incomplete(status)),
+        }
+    }
+
+    /// Render to and read from this framebuffer. Doesn't change the viewport.
+    pub fn bind(&self) {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, self.id) }
+    }
+
+    /// Read from this framebuffer, e.g. as the source of a blit.
+    pub fn bind_read(&self) {
+        unsafe { gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id) }
+    }
+
+    /// Render to this framebuffer.
+    pub fn bind_draw(&self) {
+        unsafe { gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.id) }
+    }
+
+    /// Render to and read from the window again.
+    pub fn bind_default() {
+        unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) }
+    }
+
+    /// Bind the framebuffer and set the viewport to cover it, until the guard is dropped
+    /// and the previous framebuffers and viewport are restored.
+    pub fn bind_scoped(&self) -> FramebufferGuard<'_> {
+        let guard = FramebufferGuard::save();
+        self.bind();
+        if let Some((width, height)) = self.size() {
+            unsafe { gl::Viewport(0, 0, width, height) };
+        }
+        guard
+    }
+
+    /// The size of the smallest attachment, `None` without attachments.
+    pub fn size(&self) -> Option<(i32, i32)> {
+        self.colors
+            .iter()
+            .map(|attachment| attachment.size)
+            .chain(self.depth)
+            .chain(self.stencil)
+            .reduce(|(w, h), (width, height)| (w.min(width), h.min(height)))
+    }
+
+    /// Run `f` with the framebuffer bound, then bind the previous ones again.
+    fn with_bound<R>(&self, f: impl FnOnce() -> R) -> R {
+        let (mut draw, mut read) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, ptr::addr_of_mut!(draw));
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, ptr::addr_of_mut!(read));
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+        }
+        let result = f();
+        unsafe {
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw as u32);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read as u32);
+        }
+        result
+    }
+}
+
+impl Default for Framebuffer<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Framebuffer<'_> {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteFramebuffers(1, ptr::addr_of!(self.id)) }
+    }
+}
+
+/// Restores the framebuffers and viewport that were in use before
+/// [`Framebuffer::bind_scoped`] when dropped.
+#[must_use = "the previous framebuffer is restored as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct FramebufferGuard<'a> {
+    draw: u32,
+    read: u32,
+    viewport: [i32; 4],
+    framebuffer: PhantomData<&'a Framebuffer<'a>>,
+}
+
+impl FramebufferGuard<'_> {
+    fn save() -> Self {
+        let (mut draw, mut read) = (0, 0);
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, ptr::addr_of_mut!(draw));
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, ptr::addr_of_mut!(read));
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+
+        Self {
+            draw: draw as u32,
+            read: read as u32,
+            viewport,
+            framebuffer: PhantomData,
+        }
+    }
+}
+
+impl Drop for FramebufferGuard<'_> {
+    fn drop(&mut self) {
+        let [x, y, width, height] = self.viewport;
+        unsafe {
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.draw);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.read);
+            gl::Viewport(x, y, width, height);
+        }
+    }
+}
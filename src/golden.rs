@@ -0,0 +1,290 @@
+use crate::{AnyError, HeadlessContext, HeadlessError, capture_framebuffer};
+use core::{error, fmt};
+use image::{ImageError, Rgba, RgbaImage};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// The largest YIQ difference between two pixels, black against white.
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Headless(HeadlessError),
+    /// The scene closure failed.
+    Scene(AnyError),
+    Image(ImageError),
+    /// There's no reference image yet, run with `UPDATE_GOLDENS=1` to create it.
+    MissingGolden(PathBuf),
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// More pixels differ than allowed.
+    Mismatch(GoldenReport),
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Headless(e) => e.fmt(f),
+            Self::Scene(e) => write!(f, "Cannot render the scene: {e}"),
+            Self::Image(e) => e.fmt(f),
+            Self::MissingGolden(path) => write!(
+                f,
+                "No golden image at {}, run with UPDATE_GOLDENS=1 to create it",
+                path.display()
+            ),
+            Self::SizeMismatch { expected, actual } => write!(
+                f,
+                "The image is {}x{}, but the golden image is {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            Self::Mismatch(report) => write!(
+                f,
+                "{} of {} pixels differ from {} (largest channel difference {}, mean perceptual difference {:.4}), see {}",
+                report.differing_pixels,
+                report.total_pixels,
+                report.golden.display(),
+                report.max_channel_difference,
+                report.mean_perceptual_difference,
+                report.diff.display(),
+            ),
+        }
+    }
+}
+
+impl error::Error for GoldenError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Headless(e) => Some(e),
+            Self::Scene(e) => Some(e.as_ref()),
+            Self::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<HeadlessError> for GoldenError {
+    fn from(value: HeadlessError) -> Self {
+        Self::Headless(value)
+    }
+}
+
+impl From<ImageError> for GoldenError {
+    fn from(value: ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+/// How an image compared to its golden image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenReport {
+    pub golden: PathBuf,
+    /// Where the diff image is written when the comparison fails.
+    pub diff: PathBuf,
+    pub total_pixels: usize,
+    /// Pixels outside of both the channel tolerance and the perceptual threshold.
+    pub differing_pixels: usize,
+    pub max_channel_difference: u8,
+    /// The mean YIQ difference over every pixel, from `0.0` for equal images to `1.0`
+    /// for black against white.
+    pub mean_perceptual_difference: f32,
+}
+
+/// Compares rendered images with reference PNGs.
+///
+/// ```ignore
+/// #[test]
+/// fn triangle() {
+///     GoldenTest::new("triangle")
+///         .render(64, 64, |_| {
+///             draw_triangle()?;
+///             Ok(())
+///         })
+///         .unwrap();
+/// }
+/// ```
+///
+/// Golden images live in `tests/goldens`, set `UPDATE_GOLDENS=1` to write them from the
+/// current output instead of comparing. Failures leave the rendered and the diff image
+/// in `target/golden-failures`.
+#[derive(Debug, Clone)]
+pub struct GoldenTest {
+    name: String,
+    directory: PathBuf,
+    failure_directory: PathBuf,
+    channel_tolerance: u8,
+    perceptual_threshold: f32,
+    allowed_pixels: usize,
+}
+
+impl GoldenTest {
+    pub fn new(name: impl Into<String>) -> Self {
+        // cargo sets this for tests, fall back to the working directory otherwise
+        let root = env::var_os("CARGO_MANIFEST_DIR").map_or_else(PathBuf::new, PathBuf::from);
+
+        Self {
+            name: name.into(),
+            directory: root.join("tests").join("goldens"),
+            failure_directory: root.join("target").join("golden-failures"),
+            channel_tolerance: 2,
+            perceptual_threshold: 0.1,
+            allowed_pixels: 0,
+        }
+    }
+
+    /// Where the golden images are stored.
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = directory.into();
+        self
+    }
+
+    /// Where the rendered and diff images of failed comparisons are written.
+    pub fn failure_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.failure_directory = directory.into();
+        self
+    }
+
+    /// Pixels whose channels all differ by at most this much are equal. Defaults to 2.
+    pub fn channel_tolerance(mut self, tolerance: u8) -> Self {
+        self.channel_tolerance = tolerance;
+        self
+    }
+
+    /// Pixels with a smaller perceptual difference are equal, from `0.0` to `1.0`.
+    /// Defaults to `0.1`, which hides anti-aliasing differences between rasterizers.
+    pub fn perceptual_threshold(mut self, threshold: f32) -> Self {
+        self.perceptual_threshold = threshold;
+        self
+    }
+
+    /// How many pixels may differ before the comparison fails. Defaults to 0.
+    pub fn allowed_pixels(mut self, pixels: usize) -> Self {
+        self.allowed_pixels = pixels;
+        self
+    }
+
+    /// Render `scene` into a fresh [`HeadlessContext`] and compare the result.
+    pub fn render(
+        &self,
+        width: i32,
+        height: i32,
+        scene: impl FnOnce(&HeadlessContext) -> Result<(), AnyError>,
+    ) -> Result<GoldenReport, GoldenError> {
+        let context = HeadlessContext::new(width, height)?;
+        scene(&context).map_err(GoldenError::Scene)?;
+
+        // the scene may have left another framebuffer or viewport behind
+        context.framebuffer().bind();
+        unsafe { gl::Viewport(0, 0, width, height) };
+        let image = capture_framebuffer();
+        drop(context);
+
+        self.compare(&image)
+    }
+
+    /// Compare `image` with the golden image, or replace it with `UPDATE_GOLDENS=1`.
+    pub fn compare(&self, image: &RgbaImage) -> Result<GoldenReport, GoldenError> {
+        let golden_path = self.directory.join(format!("{}.png", self.name));
+        let diff_path = self
+            .failure_directory
+            .join(format!("{}.diff.png", self.name));
+
+        if env::var_os("UPDATE_GOLDENS").is_some_and(|update| update != "0") {
+            create_parent(&golden_path)?;
+            image.save(&golden_path)?;
+        }
+
+        if !golden_path.exists() {
+            return Err(GoldenError::MissingGolden(golden_path));
+        }
+        let golden = image::open(&golden_path)?.to_rgba8();
+        if golden.dimensions() != image.dimensions() {
+            return Err(GoldenError::SizeMismatch {
+                expected: golden.dimensions(),
+                actual: image.dimensions(),
+            });
+        }
+
+        let mut diff = RgbaImage::new(image.width(), image.height());
+        let mut report = GoldenReport {
+            golden: golden_path,
+            diff: diff_path,
+            total_pixels: image.pixels().len(),
+            differing_pixels: 0,
+            max_channel_difference: 0,
+            mean_perceptual_difference: 0.0,
+        };
+
+        let mut perceptual_sum = 0.0;
+        for ((expected, actual), diff_pixel) in
+            golden.pixels().zip(image.pixels()).zip(diff.pixels_mut())
+        {
+            let channel_difference = (0..4)
+                .map(|c| expected[c].abs_diff(actual[c]))
+                .max()
+                .unwrap_or(0);
+            let perceptual = perceptual_difference(expected, actual);
+
+            report.max_channel_difference = report.max_channel_difference.max(channel_difference);
+            perceptual_sum += perceptual as f64;
+
+            let differs = channel_difference > self.channel_tolerance
+                && perceptual > self.perceptual_threshold;
+            if differs {
+                report.differing_pixels += 1;
+            }
+            *diff_pixel = diff_color(expected, perceptual, differs);
+        }
+        report.mean_perceptual_difference = (perceptual_sum / report.total_pixels as f64) as f32;
+
+        if report.differing_pixels > self.allowed_pixels {
+            create_parent(&report.diff)?;
+            diff.save(&report.diff)?;
+            image.save(self.failure_directory.join(format!("{}.png", self.name)))?;
+            return Err(GoldenError::Mismatch(report));
+        }
+
+        Ok(report)
+    }
+}
+
+fn create_parent(path: &Path) -> Result<(), ImageError> {
+    match path.parent() {
+        Some(parent) => std::fs::create_dir_all(parent).map_err(ImageError::IoError),
+        None => Ok(()),
+    }
+}
+
+/// The YIQ difference of two pixels blended onto white, `0.0..=1.0`,
+/// the metric of pixelmatch.
+fn perceptual_difference(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let yiq = |pixel: &Rgba<u8>| {
+        let alpha = pixel[3] as f32 / 255.0;
+        let [r, g, b] = [0, 1, 2].map(|c| 255.0 + (pixel[c] as f32 - 255.0) * alpha);
+        [
+            r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_23,
+            r * 0.595_977_97 - g * 0.274_176_1 - b * 0.321_801_9,
+            r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94,
+        ]
+    };
+
+    let ([y1, i1, q1], [y2, i2, q2]) = (yiq(a), yiq(b));
+    let delta = 0.5053 * (y1 - y2).powi(2) + 0.299 * (i1 - i2).powi(2) + 0.1957 * (q1 - q2).powi(2);
+    (delta / MAX_YIQ_DELTA).sqrt().min(1.0)
+}
+
+/// Differing pixels in red, brighter the bigger the difference, over a faded
+/// grayscale copy of the golden image.
+fn diff_color(expected: &Rgba<u8>, perceptual: f32, differs: bool) -> Rgba<u8> {
+    if differs {
+        let intensity = (128.0 + perceptual * 127.0) as u8;
+        return Rgba([intensity, 0, 0, 255]);
+    }
+
+    let luma = expected[0] as f32 * 0.299 + expected[1] as f32 * 0.587 + expected[2] as f32 * 0.114;
+    let faded = (255.0 - (255.0 - luma) * 0.1) as u8;
+    Rgba([faded, faded, faded, 255])
+}
@@ -0,0 +1,498 @@
+use crate::{
+    Framebuffer, FramebufferError, Renderbuffer, TextureError, TextureFormat, TextureUnits,
+    sprite::release_sprite_data,
+};
+use core::{error, fmt};
+use libloading::Library;
+use std::{
+    ffi::{CString, c_char, c_void},
+    mem, ptr,
+    sync::{Mutex, PoisonError},
+};
+
+/// The backend the `gl` function pointers were loaded from. They are process wide
+/// statics, so they are written once instead of by every context.
+static LOADED_BACKEND: Mutex<Option<HeadlessBackend>> = Mutex::new(None);
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// The EGL or OSMesa library couldn't be loaded, or misses a function.
+    Library(libloading::Error),
+    /// A context call failed, with the error code of the backend.
+    Call {
+        function: &'static str,
+        error: i32,
+    },
+    Framebuffer(FramebufferError),
+    Texture(TextureError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Library(e) => write!(f, "Cannot load the context library: {e}"),
+            Self::Call { function, error } => write!(f, "{function} failed with {error:#x}"),
+            Self::Framebuffer(e) => e.fmt(f),
+            Self::Texture(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for HeadlessError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Library(e) => Some(e),
+            Self::Call { .. } => None,
+            Self::Framebuffer(e) => Some(e),
+            Self::Texture(e) => Some(e),
+        }
+    }
+}
+
+impl From<libloading::Error> for HeadlessError {
+    fn from(value: libloading::Error) -> Self {
+        Self::Library(value)
+    }
+}
+
+impl From<FramebufferError> for HeadlessError {
+    fn from(value: FramebufferError) -> Self {
+        Self::Framebuffer(value)
+    }
+}
+
+impl From<TextureError> for HeadlessError {
+    fn from(value: TextureError) -> Self {
+        Self::Texture(value)
+    }
+}
+
+mod egl {
+    use std::ffi::{c_char, c_void};
+
+    pub type Display = *mut c_void;
+    pub type Config = *mut c_void;
+    pub type Context = *mut c_void;
+
+    pub const PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+    pub const RED_SIZE: i32 = 0x3024;
+    pub const GREEN_SIZE: i32 = 0x3023;
+    pub const BLUE_SIZE: i32 = 0x3022;
+    pub const ALPHA_SIZE: i32 = 0x3021;
+    pub const SURFACE_TYPE: i32 = 0x3033;
+    pub const RENDERABLE_TYPE: i32 = 0x3040;
+    pub const OPENGL_BIT: i32 = 0x0008;
+    pub const NONE: i32 = 0x3038;
+    pub const OPENGL_API: u32 = 0x30A2;
+    pub const CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+    pub const CONTEXT_MINOR_VERSION: i32 = 0x30FB;
+    pub const CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30FD;
+    pub const CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 0x0001;
+
+    pub type GetProcAddress = unsafe extern "C" fn(*const c_char) -> *const c_void;
+    pub type GetPlatformDisplay = unsafe extern "C" fn(u32, *mut c_void, *const i32) -> Display;
+    pub type GetDisplay = unsafe extern "C" fn(*mut c_void) -> Display;
+    pub type Initialize = unsafe extern "C" fn(Display, *mut i32, *mut i32) -> u32;
+    pub type BindApi = unsafe extern "C" fn(u32) -> u32;
+    pub type ChooseConfig =
+        unsafe extern "C" fn(Display, *const i32, *mut Config, i32, *mut i32) -> u32;
+    pub type CreateContext = unsafe extern "C" fn(Display, Config, Context, *const i32) -> Context;
+    pub type MakeCurrent = unsafe extern "C" fn(Display, *mut c_void, *mut c_void, Context) -> u32;
+    pub type DestroyContext = unsafe extern "C" fn(Display, Context) -> u32;
+    pub type GetError = unsafe extern "C" fn() -> i32;
+}
+
+mod osmesa {
+    use std::ffi::{c_char, c_void};
+
+    pub type Context = *mut c_void;
+
+    pub const FORMAT: i32 = 0x22;
+    pub const DEPTH_BITS: i32 = 0x30;
+    pub const STENCIL_BITS: i32 = 0x31;
+    pub const PROFILE: i32 = 0x33;
+    pub const CORE_PROFILE: i32 = 0x34;
+    pub const CONTEXT_MAJOR_VERSION: i32 = 0x36;
+    pub const CONTEXT_MINOR_VERSION: i32 = 0x37;
+
+    pub type CreateContextAttribs = unsafe extern "C" fn(*const i32, Context) -> Context;
+    pub type MakeCurrent = unsafe extern "C" fn(Context, *mut c_void, u32, i32, i32) -> u8;
+    pub type GetProcAddress = unsafe extern "C" fn(*const c_char) -> *const c_void;
+    pub type DestroyContext = unsafe extern "C" fn(Context);
+}
+
+/// The library a [`HeadlessContext`] gets its context from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadlessBackend {
+    /// EGL on the surfaceless platform, hardware accelerated if there's a GPU.
+    Egl,
+    /// Mesa's software renderer, for machines without EGL.
+    OsMesa,
+}
+
+enum Backend {
+    Egl {
+        display: egl::Display,
+        context: egl::Context,
+        make_current: egl::MakeCurrent,
+        destroy_context: egl::DestroyContext,
+    },
+    OsMesa {
+        context: osmesa::Context,
+        make_current: osmesa::MakeCurrent,
+        destroy_context: osmesa::DestroyContext,
+        /// OSMesa needs a buffer, drawing goes to the framebuffer anyway.
+        buffer: Box<[u8; 4]>,
+    },
+}
+
+impl Backend {
+    /// Make the context current on this thread again, another one may have replaced it.
+    unsafe fn make_current(&mut self) {
+        unsafe {
+            match self {
+                Self::Egl {
+                    display,
+                    context,
+                    make_current,
+                    ..
+                } => {
+                    make_current(*display, ptr::null_mut(), ptr::null_mut(), *context);
+                }
+                Self::OsMesa {
+                    context,
+                    make_current,
+                    buffer,
+                    ..
+                } => {
+                    make_current(
+                        *context,
+                        buffer.as_mut_ptr() as *mut c_void,
+                        gl::UNSIGNED_BYTE,
+                        1,
+                        1,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// A GL 3.3 core context without a window, for tests and CI machines without a display.
+///
+/// Everything is drawn into an offscreen framebuffer of the given size, which is
+/// bound and covered by the viewport like the framebuffer of a window would be:
+///
+/// ```ignore
+/// let context = HeadlessContext::new(640, 480)?;
+/// // draw like in a window
+/// capture_framebuffer().save("frame.png")?;
+/// ```
+///
+/// The context is current on the creating thread until another one is created there.
+/// Dropping it makes it current again to delete its objects, and leaves no context current.
+/// The EGL or OSMesa library stays loaded for the rest of the process.
+pub struct HeadlessContext {
+    width: i32,
+    height: i32,
+    /// Deleted before the context is destroyed.
+    targets: Option<(Framebuffer<'static>, Renderbuffer, Renderbuffer)>,
+    backend: Backend,
+}
+
+impl HeadlessContext {
+    /// Create a context with EGL, or OSMesa if that fails.
+    pub fn new(width: i32, height: i32) -> Result<Self, HeadlessError> {
+        Self::with_backend(HeadlessBackend::Egl, width, height).or_else(|egl_error| {
+            match Self::with_backend(HeadlessBackend::OsMesa, width, height) {
+                // the EGL error says more if OSMesa isn't even installed
+                Err(HeadlessError::Library(_)) => Err(egl_error),
+                result => result,
+            }
+        })
+    }
+
+    pub fn with_backend(
+        backend: HeadlessBackend,
+        width: i32,
+        height: i32,
+    ) -> Result<Self, HeadlessError> {
+        if width <= 0 || height <= 0 {
+            return Err(TextureError::InvalidSize { width, height }.into());
+        }
+
+        let backend = unsafe {
+            match backend {
+                HeadlessBackend::Egl => create_egl()?,
+                HeadlessBackend::OsMesa => create_osmesa()?,
+            }
+        };
+        // bindings of a previous context on this thread mean nothing here
+        TextureUnits::invalidate();
+
+        let mut context = Self {
+            width,
+            height,
+            targets: None,
+            backend,
+        };
+
+        let color = Renderbuffer::new(width, height, TextureFormat::Rgba8, 0)?;
+        let depth = Renderbuffer::new(width, height, TextureFormat::Depth24Stencil8, 0)?;
+        let mut framebuffer = Framebuffer::new();
+        // both live next to the framebuffer in `targets`, which drops it first
+        framebuffer.attach_color_untracked(0, &color)?;
+        framebuffer.attach_depth_stencil_untracked(&depth)?;
+        framebuffer.check()?;
+
+        framebuffer.bind();
+        unsafe { gl::Viewport(0, 0, width, height) };
+        context.targets = Some((framebuffer, color, depth));
+
+        Ok(context)
+    }
+
+    /// The framebuffer standing in for the window, bind it again after drawing elsewhere.
+    pub fn framebuffer(&self) -> &Framebuffer<'static> {
+        &self
+            .targets
+            .as_ref()
+            .expect("The context has a framebuffer")
+            .0
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        // the objects below belong to this context, not whichever is current
+        unsafe { self.backend.make_current() };
+        self.targets = None;
+        release_sprite_data();
+        TextureUnits::invalidate();
+
+        unsafe {
+            match self.backend {
+                Backend::Egl {
+                    display,
+                    context,
+                    make_current,
+                    destroy_context,
+                } => {
+                    // the display is shared with the contexts of other threads,
+                    // so it stays initialized
+                    make_current(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+                    destroy_context(display, context);
+                }
+                Backend::OsMesa {
+                    context,
+                    destroy_context,
+                    ..
+                } => destroy_context(context),
+            }
+        }
+    }
+}
+
+/// Load a function from `library`, copied out so it outlives the borrow.
+unsafe fn symbol<T: Copy>(library: &Library, name: &[u8]) -> Result<T, HeadlessError> {
+    Ok(unsafe { *library.get::<T>(name)? })
+}
+
+unsafe fn create_egl() -> Result<Backend, HeadlessError> {
+    unsafe {
+        let library = Library::new("libEGL.so.1").or_else(|_| Library::new("libEGL.so"))?;
+        let get_proc_address: egl::GetProcAddress = symbol(&library, b"eglGetProcAddress\0")?;
+        let get_display: egl::GetDisplay = symbol(&library, b"eglGetDisplay\0")?;
+        let initialize: egl::Initialize = symbol(&library, b"eglInitialize\0")?;
+        let bind_api: egl::BindApi = symbol(&library, b"eglBindAPI\0")?;
+        let choose_config: egl::ChooseConfig = symbol(&library, b"eglChooseConfig\0")?;
+        let create_context: egl::CreateContext = symbol(&library, b"eglCreateContext\0")?;
+        let make_current: egl::MakeCurrent = symbol(&library, b"eglMakeCurrent\0")?;
+        let destroy_context: egl::DestroyContext = symbol(&library, b"eglDestroyContext\0")?;
+        let get_error: egl::GetError = symbol(&library, b"eglGetError\0")?;
+
+        let failed = |function| HeadlessError::Call {
+            function,
+            error: get_error(),
+        };
+
+        let get_platform_display = get_proc_address(c"eglGetPlatformDisplayEXT".as_ptr());
+        let display = if get_platform_display.is_null() {
+            get_display(ptr::null_mut())
+        } else {
+            let get_platform_display: egl::GetPlatformDisplay =
+                std::mem::transmute(get_platform_display);
+            get_platform_display(egl::PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null())
+        };
+        if display.is_null() {
+            return Err(failed("eglGetPlatformDisplayEXT"));
+        }
+
+        let (mut major, mut minor) = (0, 0);
+        if initialize(display, &mut major, &mut minor) == 0 {
+            return Err(failed("eglInitialize"));
+        }
+        if bind_api(egl::OPENGL_API) == 0 {
+            return Err(failed("eglBindAPI"));
+        }
+
+        let config_attributes = [
+            egl::RED_SIZE,
+            8,
+            egl::GREEN_SIZE,
+            8,
+            egl::BLUE_SIZE,
+            8,
+            egl::ALPHA_SIZE,
+            8,
+            // the default asks for window surfaces, which surfaceless displays don't have
+            egl::SURFACE_TYPE,
+            0,
+            egl::RENDERABLE_TYPE,
+            egl::OPENGL_BIT,
+            egl::NONE,
+        ];
+        let mut config = ptr::null_mut();
+        let mut configs = 0;
+        if choose_config(
+            display,
+            config_attributes.as_ptr(),
+            &mut config,
+            1,
+            &mut configs,
+        ) == 0
+            || configs == 0
+        {
+            let error = failed("eglChooseConfig");
+            return Err(error);
+        }
+
+        let context_attributes = [
+            egl::CONTEXT_MAJOR_VERSION,
+            3,
+            egl::CONTEXT_MINOR_VERSION,
+            3,
+            egl::CONTEXT_OPENGL_PROFILE_MASK,
+            egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+            egl::NONE,
+        ];
+        let context = create_context(
+            display,
+            config,
+            ptr::null_mut(),
+            context_attributes.as_ptr(),
+        );
+        if context.is_null() {
+            let error = failed("eglCreateContext");
+            return Err(error);
+        }
+
+        // without surfaces, which needs EGL_KHR_surfaceless_context
+        if make_current(display, ptr::null_mut(), ptr::null_mut(), context) == 0 {
+            let error = failed("eglMakeCurrent");
+            destroy_context(display, context);
+            return Err(error);
+        }
+
+        load_gl(HeadlessBackend::Egl, |name| get_proc_address(name));
+
+        let backend = Backend::Egl {
+            display,
+            context,
+            make_current,
+            destroy_context,
+        };
+        // the `gl` function pointers and the ones above point into the library
+        mem::forget(library);
+        Ok(backend)
+    }
+}
+
+unsafe fn create_osmesa() -> Result<Backend, HeadlessError> {
+    unsafe {
+        let library = Library::new("libOSMesa.so.8").or_else(|_| Library::new("libOSMesa.so"))?;
+        let create_context: osmesa::CreateContextAttribs =
+            symbol(&library, b"OSMesaCreateContextAttribs\0")?;
+        let make_current: osmesa::MakeCurrent = symbol(&library, b"OSMesaMakeCurrent\0")?;
+        let get_proc_address: osmesa::GetProcAddress = symbol(&library, b"OSMesaGetProcAddress\0")?;
+        let destroy_context: osmesa::DestroyContext = symbol(&library, b"OSMesaDestroyContext\0")?;
+
+        let attributes = [
+            osmesa::FORMAT,
+            gl::RGBA as i32,
+            osmesa::DEPTH_BITS,
+            0,
+            osmesa::STENCIL_BITS,
+            0,
+            osmesa::PROFILE,
+            osmesa::CORE_PROFILE,
+            osmesa::CONTEXT_MAJOR_VERSION,
+            3,
+            osmesa::CONTEXT_MINOR_VERSION,
+            3,
+            0,
+        ];
+        let context = create_context(attributes.as_ptr(), ptr::null_mut());
+        if context.is_null() {
+            return Err(HeadlessError::Call {
+                function: "OSMesaCreateContextAttribs",
+                error: 0,
+            });
+        }
+
+        let mut buffer = Box::new([0_u8; 4]);
+        if make_current(
+            context,
+            buffer.as_mut_ptr() as *mut c_void,
+            gl::UNSIGNED_BYTE,
+            1,
+            1,
+        ) == 0
+        {
+            destroy_context(context);
+            return Err(HeadlessError::Call {
+                function: "OSMesaMakeCurrent",
+                error: 0,
+            });
+        }
+
+        load_gl(HeadlessBackend::OsMesa, |name| get_proc_address(name));
+
+        let backend = Backend::OsMesa {
+            context,
+            make_current,
+            destroy_context,
+            buffer,
+        };
+        // the `gl` function pointers and the ones above point into the library
+        mem::forget(library);
+        Ok(backend)
+    }
+}
+
+/// Load the `gl` functions, unless another context of the same backend already did.
+///
+/// Switching backends replaces the pointers while contexts of the other backend
+/// may still use them, so one process should stick to one backend.
+fn load_gl(backend: HeadlessBackend, get_proc_address: impl Fn(*const c_char) -> *const c_void) {
+    let mut loaded = LOADED_BACKEND
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if *loaded == Some(backend) {
+        return;
+    }
+
+    gl::load_with(|name| {
+        let name = CString::new(name).expect("GL function names have no nul bytes");
+        get_proc_address(name.as_ptr())
+    });
+    *loaded = Some(backend);
+}
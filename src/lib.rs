@@ -1,14 +1,56 @@
+extern crate self as gl_tests_god_save_me;
+
 use core::ops;
 
+pub mod atlas;
+mod block_decode;
 pub mod buffer;
 pub mod camera;
+pub mod compressed;
+pub mod compressed_image;
+pub mod cubemap;
+pub mod diagnostics;
+pub mod framebuffer;
+#[cfg(feature = "golden")]
+pub mod golden;
+#[cfg(feature = "headless")]
+pub mod headless;
+pub mod multisample;
+pub mod pipeline;
+pub mod pixel_buffer;
+pub mod preprocessor;
+pub mod program_builder;
+pub mod program_cache;
+pub mod reflection;
+pub mod reload;
+pub mod sampler;
 pub mod shader;
+pub mod skybox;
 pub mod sprite;
 pub mod sprite_sheet;
 pub mod texture;
+pub mod texture_array;
+pub mod texture_format;
+#[cfg(feature = "image")]
+pub mod texture_image;
+pub mod texture_units;
+pub mod uniforms;
 pub mod vao;
 
-pub use {buffer::*, camera::*, shader::*, sprite::*, sprite_sheet::*, texture::*, vao::*};
+pub use gl_tests_god_save_me_macros::{Uniforms, glsl};
+#[cfg(feature = "golden")]
+pub use golden::*;
+#[cfg(feature = "headless")]
+pub use headless::*;
+#[cfg(feature = "image")]
+pub use texture_image::*;
+pub use {
+    atlas::*, buffer::*, camera::*, compressed::*, compressed_image::*, cubemap::*, diagnostics::*,
+    framebuffer::*, multisample::*, pipeline::*, pixel_buffer::*, preprocessor::*,
+    program_builder::*, program_cache::*, reflection::*, reload::*, sampler::*, shader::*,
+    skybox::*, sprite::*, sprite_sheet::*, texture::*, texture_array::*, texture_format::*,
+    texture_units::*, uniforms::*, vao::*,
+};
 pub type AnyError = Box<dyn std::error::Error>;
 
 pub struct ClearFlags(u32);
@@ -0,0 +1,312 @@
+use crate::{FormatKind, Texture, TextureError, TextureFormat, TextureObject, TextureUnits};
+use std::{fmt::Debug, ptr};
+
+/// The most samples a multisampled image of `format` can have.
+pub fn max_samples(format: TextureFormat) -> i32 {
+    let limit = match format.kind() {
+        FormatKind::SignedInteger | FormatKind::UnsignedInteger => gl::MAX_INTEGER_SAMPLES,
+        _ => gl::MAX_SAMPLES,
+    };
+
+    let mut max = 0;
+    unsafe { gl::GetIntegerv(limit, ptr::addr_of_mut!(max)) };
+    max
+}
+
+fn check_samples(samples: i32, format: TextureFormat) -> Result<(), TextureError> {
+    let max = max_samples(format);
+    if samples < 1 || samples > max {
+        return Err(TextureError::InvalidSampleCount { samples, max });
+    }
+
+    Ok(())
+}
+
+/// A texture with several samples per pixel, read with `texelFetch` through a
+/// `sampler2DMS`, or resolved into a [`Texture`].
+pub struct Texture2DMultisample {
+    id: u32,
+    width: i32,
+    height: i32,
+    format: TextureFormat,
+    samples: i32,
+}
+
+impl Texture2DMultisample {
+    /// Create a texture with undefined contents and `samples` samples per pixel,
+    /// at most [`max_samples`].
+    pub fn new(
+        width: i32,
+        height: i32,
+        format: TextureFormat,
+        samples: i32,
+    ) -> Result<Self, TextureError> {
+        if width <= 0 || height <= 0 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+        check_samples(samples, format)?;
+
+        let mut id = 0_u32;
+        unsafe {
+            gl::GenTextures(1, ptr::addr_of_mut!(id));
+            TextureUnits::bind_for_update(gl::TEXTURE_2D_MULTISAMPLE, id);
+
+            // fixed sample locations, so it can be attached next to renderbuffers
+            if gl::TexStorage2DMultisample::is_loaded() {
+                gl::TexStorage2DMultisample(
+                    gl::TEXTURE_2D_MULTISAMPLE,
+                    samples,
+                    format as u32,
+                    width,
+                    height,
+                    gl::TRUE,
+                );
+            } else {
+                gl::TexImage2DMultisample(
+                    gl::TEXTURE_2D_MULTISAMPLE,
+                    samples,
+                    format as u32,
+                    width,
+                    height,
+                    gl::TRUE,
+                );
+            }
+        }
+
+        Ok(Self {
+            id,
+            width,
+            height,
+            format,
+            samples,
+        })
+    }
+
+    /// Average the samples into the base level of `target`, which needs the same
+    /// size and format.
+    pub fn resolve(&self, target: &Texture) -> Result<(), TextureError> {
+        resolve(
+            Source::Texture(self.id),
+            self.width,
+            self.height,
+            self.format,
+            target,
+        )
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub fn samples(&self) -> i32 {
+        self.samples
+    }
+}
+
+impl TextureObject for Texture2DMultisample {
+    fn raw_id(&self) -> u32 {
+        self.id
+    }
+
+    fn target(&self) -> u32 {
+        gl::TEXTURE_2D_MULTISAMPLE
+    }
+}
+
+impl Debug for Texture2DMultisample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Texture2DMultisample")
+            .field("id", &self.id)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
+            .field("samples", &self.samples)
+            .finish()
+    }
+}
+
+impl Drop for Texture2DMultisample {
+    fn drop(&mut self) {
+        TextureUnits::forget_texture(self.id);
+        unsafe { gl::DeleteTextures(1, ptr::addr_of!(self.id)) }
+    }
+}
+
+/// An image that can only be rendered to, cheaper than a texture when
+/// nothing samples it, e.g. a depth buffer.
+#[derive(Debug)]
+pub struct Renderbuffer {
+    pub(crate) id: u32,
+    width: i32,
+    height: i32,
+    format: TextureFormat,
+    samples: i32,
+}
+
+impl Renderbuffer {
+    /// Create a renderbuffer with undefined contents. `0` samples creates
+    /// a regular image, otherwise there are at most [`max_samples`].
+    pub fn new(
+        width: i32,
+        height: i32,
+        format: TextureFormat,
+        samples: i32,
+    ) -> Result<Self, TextureError> {
+        if width <= 0 || height <= 0 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+        if samples != 0 {
+            check_samples(samples, format)?;
+        }
+
+        let mut id = 0_u32;
+        unsafe {
+            gl::GenRenderbuffers(1, ptr::addr_of_mut!(id));
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples,
+                format as u32,
+                width,
+                height,
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+
+        Ok(Self {
+            id,
+            width,
+            height,
+            format,
+            samples,
+        })
+    }
+
+    /// Copy the image into the base level of `target`, which needs the same
+    /// size and format. Multisampled images are averaged.
+    pub fn resolve(&self, target: &Texture) -> Result<(), TextureError> {
+        resolve(
+            Source::Renderbuffer(self.id),
+            self.width,
+            self.height,
+            self.format,
+            target,
+        )
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// `0` if the renderbuffer isn't multisampled.
+    pub fn samples(&self) -> i32 {
+        self.samples
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteRenderbuffers(1, ptr::addr_of!(self.id)) }
+    }
+}
+
+enum Source {
+    Texture(u32),
+    Renderbuffer(u32),
+}
+
+/// The attachment point of images of `format`.
+pub(crate) const fn attachment_point(format: TextureFormat) -> u32 {
+    match format.kind() {
+        FormatKind::Depth => gl::DEPTH_ATTACHMENT,
+        FormatKind::DepthStencil => gl::DEPTH_STENCIL_ATTACHMENT,
+        _ => gl::COLOR_ATTACHMENT0,
+    }
+}
+
+/// Blit `source` into `target` through temporary framebuffers.
+fn resolve(
+    source: Source,
+    width: i32,
+    height: i32,
+    format: TextureFormat,
+    target: &Texture,
+) -> Result<(), TextureError> {
+    if target.width() != width || target.height() != height || target.format() != format {
+        return Err(TextureError::ResolveMismatch {
+            format: target.format(),
+            width: target.width(),
+            height: target.height(),
+        });
+    }
+
+    let attachment = attachment_point(format);
+    let mask = match format.kind() {
+        FormatKind::Depth => gl::DEPTH_BUFFER_BIT,
+        FormatKind::DepthStencil => gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT,
+        _ => gl::COLOR_BUFFER_BIT,
+    };
+
+    unsafe {
+        let (mut read_binding, mut draw_binding) = (0, 0);
+        gl::GetIntegerv(
+            gl::READ_FRAMEBUFFER_BINDING,
+            ptr::addr_of_mut!(read_binding),
+        );
+        gl::GetIntegerv(
+            gl::DRAW_FRAMEBUFFER_BINDING,
+            ptr::addr_of_mut!(draw_binding),
+        );
+
+        let mut framebuffers = [0_u32; 2];
+        gl::GenFramebuffers(2, framebuffers.as_mut_ptr());
+        let [read, draw] = framebuffers;
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read);
+        match source {
+            Source::Texture(id) => gl::FramebufferTexture2D(
+                gl::READ_FRAMEBUFFER,
+                attachment,
+                gl::TEXTURE_2D_MULTISAMPLE,
+                id,
+                0,
+            ),
+            Source::Renderbuffer(id) => {
+                gl::FramebufferRenderbuffer(gl::READ_FRAMEBUFFER, attachment, gl::RENDERBUFFER, id)
+            }
+        }
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw);
+        gl::FramebufferTexture2D(
+            gl::DRAW_FRAMEBUFFER,
+            attachment,
+            gl::TEXTURE_2D,
+            target.id,
+            0,
+        );
+
+        // multisampled blits need the same size, so nearest loses nothing
+        gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, mask, gl::NEAREST);
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read_binding as u32);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw_binding as u32);
+        gl::DeleteFramebuffers(2, framebuffers.as_ptr());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::{
+    AsShaderType, Fragment, InterfaceVariable, Program, ProgramInterface, Shader, ShaderError,
+    ShaderLog, ShaderType, Vertex,
+};
+use core::{marker::PhantomData, ops, ptr};
+use std::ffi::c_char;
+
+/// A program containing a single stage, linked with `GL_PROGRAM_SEPARABLE` so it can be
+/// combined with other stages in a [`ProgramPipeline`].
+///
+/// Vertex shaders using GLSL 4.10+ have to redeclare `gl_PerVertex` to be usable in a pipeline.
+pub struct SeparableProgram<S: AsShaderType> {
+    program: Program,
+    interface: Option<ProgramInterface>,
+    stage: PhantomData<S>,
+}
+
+impl<S: AsShaderType> SeparableProgram<S> {
+    pub fn new(shader: Shader<S>) -> Result<Self, ShaderError> {
+        let program = Program::builder().attach(&shader).separable(true).link()?;
+        let interface = program.interface();
+
+        Ok(Self {
+            program,
+            interface,
+            stage: PhantomData,
+        })
+    }
+
+    /// The reflected inputs and outputs, `None` if the driver can't report them.
+    pub fn interface(&self) -> Option<&ProgramInterface> {
+        self.interface.as_ref()
+    }
+}
+
+impl<S: AsShaderType> ops::Deref for SeparableProgram<S> {
+    type Target = Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
+
+/// Mixes stages from different [`SeparableProgram`]s at runtime without relinking.
+///
+/// Setting uniforms through [`Program::put_uniform`] makes that program current, so call
+/// [`ProgramPipeline::bind`] again before drawing.
+pub struct ProgramPipeline {
+    id: u32,
+    vertex: Option<Option<ProgramInterface>>,
+    fragment: Option<Option<ProgramInterface>>,
+}
+
+impl ProgramPipeline {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe { gl::GenProgramPipelines(1, ptr::addr_of_mut!(id)) };
+
+        Self {
+            id,
+            vertex: None,
+            fragment: None,
+        }
+    }
+
+    /// Use `program` for its stage, replacing whatever was used before.
+    pub fn use_stage<S: AsShaderType>(&mut self, program: &SeparableProgram<S>) {
+        let (bit, slot) = match S::as_shader_type() {
+            ShaderType::Vertex => (gl::VERTEX_SHADER_BIT, &mut self.vertex),
+            ShaderType::Fragment => (gl::FRAGMENT_SHADER_BIT, &mut self.fragment),
+        };
+
+        unsafe { gl::UseProgramStages(self.id, bit, program.id) };
+        *slot = Some(program.interface.clone());
+    }
+
+    pub fn use_vertex(&mut self, program: &SeparableProgram<Vertex>) {
+        self.use_stage(program);
+    }
+
+    pub fn use_fragment(&mut self, program: &SeparableProgram<Fragment>) {
+        self.use_stage(program);
+    }
+
+    /// Check that every fragment input is written by the vertex stage with the same type,
+    /// then let the driver validate the pipeline against the current GL state.
+    ///
+    /// The interface check is skipped if the driver can't reflect the programs.
+    pub fn validate(&self) -> Result<(), ShaderError> {
+        let (Some(vertex), Some(fragment)) = (&self.vertex, &self.fragment) else {
+            return Err(ShaderError::InterfaceMismatch(
+                "the pipeline needs both a vertex and a fragment stage".to_owned(),
+            ));
+        };
+
+        if let (Some(vertex), Some(fragment)) = (vertex, fragment) {
+            Self::match_interfaces(&vertex.outputs, &fragment.inputs)?;
+        }
+
+        let mut status = 0;
+        unsafe {
+            gl::ValidateProgramPipeline(self.id);
+            gl::GetProgramPipelineiv(self.id, gl::VALIDATE_STATUS, ptr::addr_of_mut!(status));
+        }
+
+        if status == 0 {
+            return Err(ShaderError::ValidationError(ShaderLog::parse(
+                self.info_log(),
+                &[],
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn match_interfaces(
+        outputs: &[InterfaceVariable],
+        inputs: &[InterfaceVariable],
+    ) -> Result<(), ShaderError> {
+        for input in inputs.iter().filter(|i| !i.is_builtin()) {
+            // explicit locations take precedence over names
+            let output = outputs.iter().find(|o| {
+                if input.location != -1 && o.location != -1 {
+                    o.location == input.location
+                } else {
+                    o.name == input.name
+                }
+            });
+
+            let Some(output) = output else {
+                return Err(ShaderError::InterfaceMismatch(format!(
+                    "the fragment input {} is not written by the vertex stage",
+                    input.name
+                )));
+            };
+
+            if output.gl_type != input.gl_type || output.array_size != input.array_size {
+                return Err(ShaderError::InterfaceMismatch(format!(
+                    "the vertex output {} doesn't match the type of the fragment input {}",
+                    output.name, input.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn info_log(&self) -> String {
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramPipelineiv(self.id, gl::INFO_LOG_LENGTH, ptr::addr_of_mut!(length))
+        };
+
+        let mut buffer = vec![0_u8; length.max(1) as usize];
+        let mut written = 0;
+        unsafe {
+            gl::GetProgramPipelineInfoLog(
+                self.id,
+                buffer.len() as i32,
+                ptr::addr_of_mut!(written),
+                buffer.as_mut_ptr() as *mut c_char,
+            )
+        };
+        buffer.truncate(written.max(0) as usize);
+
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Make the pipeline current. Unbinds any program set with `glUseProgram`,
+    /// since that takes precedence over the pipeline.
+    pub fn bind(&self) {
+        unsafe {
+            gl::UseProgram(0);
+            gl::BindProgramPipeline(self.id);
+        }
+    }
+}
+
+impl Default for ProgramPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgramPipelines(1, ptr::addr_of!(self.id)) }
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{Buffer, DrawTarget, DrawUsage, Texture, TextureError, TextureFormat, TextureUnits};
+use core::{error, fmt};
+use std::{
+    cell::RefCell,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    ptr, slice,
+    sync::Arc,
+    time::Duration,
+};
+
+#[derive(Debug)]
+pub enum PixelBufferError {
+    /// The buffer is still mapped, hand the pixels back with [`PixelUploadBuffer::unmap`] first.
+    Mapped,
+    /// The pixels were mapped from another buffer.
+    ForeignMapping,
+    /// `glMapBufferRange` failed.
+    MapFailed,
+    /// The driver lost the contents while the buffer was mapped, e.g. on a mode switch.
+    Corrupted,
+    Texture(TextureError),
+}
+
+impl fmt::Display for PixelBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mapped => write!(f, "The pixel buffer is still mapped"),
+            Self::ForeignMapping => write!(f, "The pixels belong to another pixel buffer"),
+            Self::MapFailed => write!(f, "Cannot map the pixel buffer"),
+            Self::Corrupted => write!(f, "The pixel buffer contents were lost while mapped"),
+            Self::Texture(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for PixelBufferError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Texture(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<TextureError> for PixelBufferError {
+    fn from(value: TextureError) -> Self {
+        Self::Texture(value)
+    }
+}
+
+/// A staging buffer for texture uploads that don't stall the frame.
+///
+/// The pixels are written into mapped GL memory, from any thread, and the
+/// copy into the texture runs on the GPU later:
+///
+/// ```ignore
+/// let mut staging = PixelUploadBuffer::new(width * height * 4);
+/// let mut pixels = staging.map()?;
+/// let worker = thread::spawn(move || {
+///     decode_into(&mut pixels);
+///     pixels
+/// });
+///
+/// // a few frames later
+/// if worker.is_finished() {
+///     staging.unmap(worker.join().unwrap())?;
+///     texture.update_region_from(&staging, 0, 0, width, height)?;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PixelUploadBuffer {
+    buffer: ManuallyDrop<Buffer>,
+    size: usize,
+    /// Shared with the [`MappedPixels`] while the buffer is mapped.
+    mapping: RefCell<Option<Arc<()>>>,
+}
+
+impl PixelUploadBuffer {
+    pub fn new(size: usize) -> Self {
+        let buffer = Buffer::new(DrawTarget::PixelUnpack);
+        buffer.bind();
+        buffer.data_empty(size, DrawUsage::StreamDraw);
+        unsafe { gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0) };
+
+        Self {
+            buffer: ManuallyDrop::new(buffer),
+            size,
+            mapping: RefCell::new(None),
+        }
+    }
+
+    /// Map the whole buffer for writing, its previous contents are discarded.
+    ///
+    /// Has to be called on the thread of the context, the pixels can then be
+    /// filled on any thread. The context has to outlive the pixels, destroying it
+    /// unmaps the memory they point to.
+    ///
+    /// Pixels dropped without [`PixelUploadBuffer::unmap`] are given up, the buffer
+    /// is unmapped and mapped again.
+    pub fn map(&mut self) -> Result<MappedPixels, PixelBufferError> {
+        self.release_abandoned();
+        if self.is_mapped() {
+            return Err(PixelBufferError::Mapped);
+        }
+
+        let data = unsafe {
+            self.buffer.bind();
+            let data = gl::MapBufferRange(
+                gl::PIXEL_UNPACK_BUFFER,
+                0,
+                self.size as isize,
+                gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT,
+            );
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+            data
+        };
+        if data.is_null() {
+            return Err(PixelBufferError::MapFailed);
+        }
+
+        let token = Arc::new(());
+        *self.mapping.get_mut() = Some(token.clone());
+        Ok(MappedPixels {
+            data: data as *mut u8,
+            len: self.size,
+            token,
+        })
+    }
+
+    /// Take the pixels back, so the buffer can be uploaded.
+    pub fn unmap(&mut self, pixels: MappedPixels) -> Result<(), PixelBufferError> {
+        if !self
+            .mapping
+            .get_mut()
+            .as_ref()
+            .is_some_and(|token| Arc::ptr_eq(token, &pixels.token))
+        {
+            return Err(PixelBufferError::ForeignMapping);
+        }
+        drop(pixels);
+
+        if !self.unmap_buffer() {
+            return Err(PixelBufferError::Corrupted);
+        }
+
+        Ok(())
+    }
+
+    /// Unmap the buffer if the pixels were dropped instead of handed back.
+    fn release_abandoned(&self) {
+        let abandoned = self
+            .mapping
+            .borrow()
+            .as_ref()
+            .is_some_and(|token| Arc::strong_count(token) == 1);
+        if abandoned {
+            // nobody reads the contents, losing them doesn't matter
+            self.unmap_buffer();
+        }
+    }
+
+    /// Returns whether the contents survived the mapping.
+    fn unmap_buffer(&self) -> bool {
+        *self.mapping.borrow_mut() = None;
+        unsafe {
+            self.buffer.bind();
+            let intact = gl::UnmapBuffer(gl::PIXEL_UNPACK_BUFFER);
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+            intact != gl::FALSE
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the buffer is mapped, including by pixels dropped without
+    /// [`PixelUploadBuffer::unmap`] until the next map or upload.
+    pub fn is_mapped(&self) -> bool {
+        self.mapping.borrow().is_some()
+    }
+}
+
+impl Drop for PixelUploadBuffer {
+    fn drop(&mut self) {
+        // deleting the buffer would free memory another thread may still write to
+        if self
+            .mapping
+            .get_mut()
+            .as_ref()
+            .is_some_and(|token| Arc::strong_count(token) > 1)
+        {
+            return;
+        }
+        unsafe { ManuallyDrop::drop(&mut self.buffer) }
+    }
+}
+
+/// The mapped memory of a [`PixelUploadBuffer`], writable from any thread.
+///
+/// Only valid while the context of the buffer exists, writing to the pixels after it was
+/// destroyed writes to unmapped memory.
+pub struct MappedPixels {
+    data: *mut u8,
+    len: usize,
+    token: Arc<()>,
+}
+
+// the mapping stays valid until the buffer gets it back, or is leaked
+unsafe impl Send for MappedPixels {}
+
+impl Deref for MappedPixels {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.data, self.len) }
+    }
+}
+
+impl DerefMut for MappedPixels {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.data, self.len) }
+    }
+}
+
+impl fmt::Debug for MappedPixels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedPixels")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl Texture {
+    /// Overwrite a part of the base level with the tightly packed pixels at the
+    /// start of `buffer`, in the format described in [`Texture::with_format`].
+    ///
+    /// Returns once the copy is queued, the CPU doesn't wait for it.
+    pub fn update_region_from(
+        &self,
+        buffer: &PixelUploadBuffer,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    ) -> Result<(), PixelBufferError> {
+        buffer.release_abandoned();
+        if buffer.is_mapped() {
+            return Err(PixelBufferError::Mapped);
+        }
+        self.check_region(x, y, width, height)?;
+
+        let expected = width as usize * height as usize * self.format().bytes_per_pixel();
+        if buffer.size < expected {
+            return Err(TextureError::DataSizeMismatch {
+                expected,
+                actual: buffer.size,
+            }
+            .into());
+        }
+
+        unsafe {
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, self.id);
+            buffer.buffer.bind();
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            // with an unpack buffer bound, the pointer is an offset into it
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x,
+                y,
+                width,
+                height,
+                self.format().pixel_format(),
+                self.format().pixel_type(),
+                ptr::null(),
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+        }
+
+        Ok(())
+    }
+
+    /// Start reading back a mip level without waiting for the GPU,
+    /// see [`Texture::read_pixels`] for the layout.
+    pub fn read_pixels_async(&self, level: i32) -> Result<PixelReadback, TextureError> {
+        let (width, height) = self.level_size(level)?;
+        let size = width as usize * height as usize * self.format().bytes_per_pixel();
+
+        let buffer = Buffer::new(DrawTarget::PixelPack);
+        buffer.bind();
+        buffer.data_empty(size, DrawUsage::StreamRead);
+
+        let fence = unsafe {
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTexImage(
+                gl::TEXTURE_2D,
+                level,
+                self.format().pixel_format(),
+                self.format().pixel_type(),
+                ptr::null_mut(),
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+            gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)
+        };
+
+        Ok(PixelReadback {
+            buffer,
+            size,
+            width,
+            height,
+            format: self.format(),
+            fence,
+        })
+    }
+}
+
+/// Pixels on their way back from the GPU, see [`Texture::read_pixels_async`].
+///
+/// Poll [`PixelReadback::is_ready`] once per frame, reading before that blocks.
+#[derive(Debug)]
+pub struct PixelReadback {
+    buffer: Buffer,
+    size: usize,
+    width: i32,
+    height: i32,
+    format: TextureFormat,
+    fence: gl::types::GLsync,
+}
+
+impl PixelReadback {
+    /// Whether the copy finished, so [`PixelReadback::read`] won't block.
+    pub fn is_ready(&self) -> bool {
+        self.wait(Duration::ZERO)
+    }
+
+    /// Wait at most `timeout` for the copy to finish, returns whether it did.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let status = unsafe {
+            gl::ClientWaitSync(
+                self.fence,
+                gl::SYNC_FLUSH_COMMANDS_BIT,
+                timeout.as_nanos().min(u64::MAX as u128) as u64,
+            )
+        };
+        matches!(status, gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED)
+    }
+
+    /// Copy the pixels out, waiting for the GPU if they aren't ready yet.
+    pub fn read(&self) -> Result<Vec<u8>, PixelBufferError> {
+        let mut pixels = vec![0; self.size];
+        unsafe {
+            self.buffer.bind();
+            let data = gl::MapBufferRange(
+                gl::PIXEL_PACK_BUFFER,
+                0,
+                self.size as isize,
+                gl::MAP_READ_BIT,
+            );
+            if data.is_null() {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                return Err(PixelBufferError::MapFailed);
+            }
+
+            ptr::copy_nonoverlapping(data as *const u8, pixels.as_mut_ptr(), self.size);
+            let intact = gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            if intact == gl::FALSE {
+                return Err(PixelBufferError::Corrupted);
+            }
+        }
+
+        Ok(pixels)
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+impl Drop for PixelReadback {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteSync(self.fence) }
+    }
+}
@@ -0,0 +1,517 @@
+use crate::{Program, ProgramCache, Shader, ShaderError, SourceFile};
+use core::{error, fmt};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+/// Somewhere to resolve `#include` directives from.
+pub trait ShaderFs {
+    fn read(&self, path: &str) -> Option<String>;
+}
+
+/// An in-memory file system, useful for shader snippets embedded with `include_str!`.
+#[derive(Debug, Default, Clone)]
+pub struct VirtualFs {
+    files: HashMap<String, String>,
+}
+
+impl VirtualFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: impl Into<String>, source: impl Into<String>) {
+        self.files
+            .insert(normalize_path(&path.into()), source.into());
+    }
+
+    pub fn with(mut self, path: impl Into<String>, source: impl Into<String>) -> Self {
+        self.insert(path, source);
+        self
+    }
+}
+
+impl ShaderFs for VirtualFs {
+    fn read(&self, path: &str) -> Option<String> {
+        self.files.get(path).cloned()
+    }
+}
+
+/// Reads includes from a directory on disk.
+#[derive(Debug, Clone)]
+pub struct DiskFs {
+    root: PathBuf,
+}
+
+impl DiskFs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl ShaderFs for DiskFs {
+    fn read(&self, path: &str) -> Option<String> {
+        fs::read_to_string(self.root.join(path)).ok()
+    }
+}
+
+/// A set of `#define`s injected into a shader, ordered so it can be used as a cache key.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Defines(BTreeMap<String, String>);
+
+impl Defines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.0.insert(name.into(), value.into());
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Define a flag without a value, like `#define USE_LIGHTING`.
+    pub fn flag(self, name: impl Into<String>) -> Self {
+        self.with(name, "")
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[derive(Debug)]
+pub enum PreprocessError {
+    IncludeNotFound {
+        path: String,
+        included_from: String,
+        line: usize,
+    },
+    IncludeCycle(Vec<String>),
+    MalformedDirective {
+        file: String,
+        line: usize,
+    },
+    MissingVersion(String),
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IncludeNotFound {
+                path,
+                included_from,
+                line,
+            } => write!(
+                f,
+                "{included_from}:{line}: cannot find included file {path}"
+            ),
+            Self::IncludeCycle(chain) => write!(f, "Include cycle: {}", chain.join(" -> ")),
+            Self::MalformedDirective { file, line } => {
+                write!(f, "{file}:{line}: malformed preprocessor directive")
+            }
+            Self::MissingVersion(file) => write!(
+                f,
+                "{file} has no #version directive and no version was configured"
+            ),
+        }
+    }
+}
+
+impl error::Error for PreprocessError {}
+
+/// The output of the [`Preprocessor`].
+#[derive(Debug, Clone)]
+pub struct PreprocessedSource {
+    pub source: String,
+    /// Original files indexed by the source string number used in the `#line` directives,
+    /// so driver errors like `2:14(3)` can be mapped back to `files[2]`, line 14.
+    pub files: Vec<SourceFile>,
+}
+
+impl PreprocessedSource {
+    pub fn file_name(&self, source_string: usize) -> Option<&str> {
+        self.files.get(source_string).map(|f| f.name.as_str())
+    }
+}
+
+/// Resolves `#include "file"` directives, injects `#define`s and normalizes `#version`.
+///
+/// Includes are resolved before the driver sees the source, which means that an
+/// `#include` inside an `#ifdef` block is always pulled in.
+/// Files containing `#pragma once` are only included a single time.
+pub struct Preprocessor<F: ShaderFs> {
+    fs: F,
+    version: Option<String>,
+    defines: Defines,
+}
+
+impl<F: ShaderFs> Preprocessor<F> {
+    pub fn new(fs: F) -> Self {
+        Self {
+            fs,
+            version: None,
+            defines: Defines::new(),
+        }
+    }
+
+    /// Force the output to use this version (e.g. `"330 core"`), replacing whatever
+    /// the files declare. Without it the highest version found in any file is used.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Add a define that is injected into every processed shader.
+    pub fn define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.set(name, value);
+        self
+    }
+
+    pub fn process(&self, entry: &str) -> Result<PreprocessedSource, PreprocessError> {
+        self.process_with(entry, &Defines::new())
+    }
+
+    /// Process a shader with additional defines on top of the ones configured on the preprocessor.
+    pub fn process_with(
+        &self,
+        entry: &str,
+        defines: &Defines,
+    ) -> Result<PreprocessedSource, PreprocessError> {
+        let entry = normalize_path(entry);
+        let mut state = State::default();
+        let source = self
+            .fs
+            .read(&entry)
+            .ok_or(PreprocessError::IncludeNotFound {
+                path: entry.clone(),
+                included_from: "<entry>".to_owned(),
+                line: 0,
+            })?;
+
+        let mut body = String::new();
+        self.expand(&entry, &source, &mut state, &mut body)?;
+
+        let version = match (&self.version, state.version) {
+            (Some(v), _) => v.clone(),
+            (None, Some((_, v))) => v,
+            (None, None) => return Err(PreprocessError::MissingVersion(entry)),
+        };
+
+        // the defines of the call replace the configured ones of the same name
+        let mut merged = self.defines.clone();
+        for (name, value) in defines.iter() {
+            merged.set(name, value);
+        }
+
+        let mut output = format!("#version {version}\n");
+        for (name, value) in merged.iter() {
+            output.push_str(&format!("#define {name} {value}\n"));
+        }
+        output.push_str(&body);
+
+        Ok(PreprocessedSource {
+            source: output,
+            files: state.files,
+        })
+    }
+
+    fn expand(
+        &self,
+        path: &str,
+        source: &str,
+        state: &mut State,
+        output: &mut String,
+    ) -> Result<(), PreprocessError> {
+        if state.once.iter().any(|p| p == path) {
+            return Ok(());
+        }
+        if state.stack.iter().any(|p| p == path) {
+            let mut chain = state.stack.clone();
+            chain.push(path.to_owned());
+            return Err(PreprocessError::IncludeCycle(chain));
+        }
+
+        let index = state.files.len();
+        state.files.push(SourceFile {
+            name: path.to_owned(),
+            source: source.to_owned(),
+        });
+        state.stack.push(path.to_owned());
+
+        // GLSL 3.30+: `#line N` makes the *next* line number N
+        output.push_str(&format!("#line 1 {index}\n"));
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            };
+            let directive = directive.trim_start();
+
+            if let Some(version) = directive.strip_prefix("version") {
+                state.record_version(version.trim());
+                // keep the line count intact for the driver
+                output.push('\n');
+            } else if let Some(include) = directive.strip_prefix("include") {
+                let name = parse_include(include).ok_or(PreprocessError::MalformedDirective {
+                    file: path.to_owned(),
+                    line: line_number,
+                })?;
+                let included = resolve_path(path, name);
+                let source =
+                    self.fs
+                        .read(&included)
+                        .ok_or_else(|| PreprocessError::IncludeNotFound {
+                            path: included.clone(),
+                            included_from: path.to_owned(),
+                            line: line_number,
+                        })?;
+
+                self.expand(&included, &source, state, output)?;
+                output.push_str(&format!("#line {} {index}\n", line_number + 1));
+            } else if directive.split_whitespace().collect::<Vec<_>>() == ["pragma", "once"] {
+                state.once.push(path.to_owned());
+                output.push('\n');
+            } else {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+
+        state.stack.pop();
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    files: Vec<SourceFile>,
+    stack: Vec<String>,
+    once: Vec<String>,
+    version: Option<(u32, String)>,
+}
+
+impl State {
+    fn record_version(&mut self, version: &str) {
+        let number = version
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or(0);
+
+        if self.version.as_ref().is_none_or(|(n, _)| number > *n) {
+            self.version = Some((number, version.to_owned()));
+        }
+    }
+}
+
+fn parse_include(rest: &str) -> Option<&str> {
+    let rest = rest.trim();
+    let rest = rest
+        .strip_prefix('"')
+        .and_then(|r| r.strip_suffix('"'))
+        .or_else(|| rest.strip_prefix('<').and_then(|r| r.strip_suffix('>')))?;
+
+    (!rest.is_empty()).then_some(rest)
+}
+
+/// Resolve `name` relative to the directory of `parent`.
+/// Names starting with `/` are relative to the root of the file system.
+fn resolve_path(parent: &str, name: &str) -> String {
+    if let Some(absolute) = name.strip_prefix('/') {
+        return normalize_path(absolute);
+    }
+
+    match parent.rsplit_once('/') {
+        Some((dir, _)) => normalize_path(&format!("{dir}/{name}")),
+        None => normalize_path(name),
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    let mut parts: Vec<&str> = vec![];
+    for part in path.split('/') {
+        match part {
+            "" | "." => (),
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    parts.join("/")
+}
+
+/// Compiled programs for every set of defines a pair of shaders has been requested with.
+pub struct ProgramPermutations<F: ShaderFs> {
+    preprocessor: Preprocessor<F>,
+    vertex_path: String,
+    fragment_path: String,
+    programs: HashMap<Defines, Program>,
+    cache: Option<ProgramCache>,
+}
+
+impl<F: ShaderFs> ProgramPermutations<F> {
+    pub fn new(
+        preprocessor: Preprocessor<F>,
+        vertex_path: impl Into<String>,
+        fragment_path: impl Into<String>,
+    ) -> Self {
+        Self {
+            preprocessor,
+            vertex_path: vertex_path.into(),
+            fragment_path: fragment_path.into(),
+            programs: HashMap::new(),
+            cache: None,
+        }
+    }
+
+    /// Store and load the compiled permutations through an on-disk binary cache.
+    pub fn with_cache(mut self, cache: ProgramCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Get the program compiled with `defines`, compiling it on first use.
+    pub fn get(&mut self, defines: &Defines) -> Result<&Program, ShaderError> {
+        if !self.programs.contains_key(defines) {
+            let vertex = self
+                .preprocessor
+                .process_with(&self.vertex_path, defines)
+                .map_err(ShaderError::Preprocess)?;
+            let fragment = self
+                .preprocessor
+                .process_with(&self.fragment_path, defines)
+                .map_err(ShaderError::Preprocess)?;
+
+            let program = match &self.cache {
+                Some(cache) => cache.link_preprocessed(&vertex, &fragment)?,
+                None => Program::new(
+                    Shader::from_preprocessed(&vertex)?,
+                    Shader::from_preprocessed(&fragment)?,
+                )?,
+            };
+            self.programs.insert(defines.clone(), program);
+        }
+
+        Ok(&self.programs[defines])
+    }
+
+    /// Drop every compiled permutation, e.g. after the shader files changed.
+    pub fn clear(&mut self) {
+        self.programs.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(fs: VirtualFs, entry: &str) -> Result<PreprocessedSource, PreprocessError> {
+        Preprocessor::new(fs).process(entry)
+    }
+
+    #[test]
+    fn resolves_relative_includes() {
+        let fs = VirtualFs::new()
+            .with(
+                "shaders/main.frag",
+                "#version 330 core\n#include \"lib/light.glsl\"\nmain",
+            )
+            .with(
+                "shaders/lib/light.glsl",
+                "#include \"../common.glsl\"\nlight",
+            )
+            .with("shaders/common.glsl", "common");
+        let output = process(fs, "shaders/main.frag").unwrap();
+
+        let names: Vec<_> = output.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "shaders/main.frag",
+                "shaders/lib/light.glsl",
+                "shaders/common.glsl"
+            ]
+        );
+        let code: Vec<_> = output
+            .source
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        assert_eq!(code, ["common", "light", "main"]);
+        assert!(output.source.starts_with("#version 330 core\n"));
+    }
+
+    #[test]
+    fn pragma_once_includes_a_file_once() {
+        let fs = VirtualFs::new()
+            .with(
+                "main.frag",
+                "#version 330 core\n#include \"a.glsl\"\n#include \"a.glsl\"",
+            )
+            .with("a.glsl", "#pragma once\nshared");
+        let output = process(fs, "main.frag").unwrap();
+
+        assert_eq!(output.source.matches("shared").count(), 1);
+    }
+
+    #[test]
+    fn reports_include_cycles() {
+        let fs = VirtualFs::new()
+            .with("main.frag", "#version 330 core\n#include \"a.glsl\"")
+            .with("a.glsl", "#include \"b.glsl\"")
+            .with("b.glsl", "#include \"a.glsl\"");
+
+        match process(fs, "main.frag") {
+            Err(PreprocessError::IncludeCycle(chain)) => {
+                assert_eq!(chain, ["main.frag", "a.glsl", "b.glsl", "a.glsl"]);
+            }
+            other => panic!("expected an include cycle, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reports_missing_includes() {
+        let fs = VirtualFs::new().with("main.frag", "#version 330 core\n\n#include <missing.glsl>");
+
+        match process(fs, "main.frag") {
+            Err(PreprocessError::IncludeNotFound {
+                path,
+                included_from,
+                line,
+            }) => assert_eq!(
+                (path.as_str(), included_from.as_str(), line),
+                ("missing.glsl", "main.frag", 3)
+            ),
+            other => panic!("expected a missing include, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn defines_of_the_call_override_configured_ones() {
+        let fs = VirtualFs::new().with("main.frag", "#version 330 core\nmain");
+        let preprocessor = Preprocessor::new(fs)
+            .define("LIGHTS", "4")
+            .define("SHADOWS", "1");
+        let output = preprocessor
+            .process_with("main.frag", &Defines::new().with("LIGHTS", "8"))
+            .unwrap();
+
+        let defines: Vec<_> = output
+            .source
+            .lines()
+            .filter(|line| line.starts_with("#define"))
+            .collect();
+        assert_eq!(defines, ["#define LIGHTS 8", "#define SHADOWS 1"]);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{AsShaderType, Program, Shader, ShaderError, ShaderLog};
+use core::{marker::PhantomData, ptr};
+use std::ffi::{CString, c_char};
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformFeedbackMode {
+    Interleaved = gl::INTERLEAVED_ATTRIBS,
+    Separate = gl::SEPARATE_ATTRIBS,
+}
+
+/// Links a [`Program`] with control over the state that has to be set before linking.
+///
+/// ```ignore
+/// let program = Program::builder()
+///     .attach(&vertex_shader)
+///     .attach(&fragment_shader)
+///     .bind_attribute(0, "position")
+///     .bind_fragment_output(0, "albedo")
+///     .bind_fragment_output(1, "normal")
+///     .link()?;
+/// ```
+pub struct ProgramBuilder<'s> {
+    shaders: Vec<u32>,
+    attributes: Vec<(u32, String)>,
+    fragment_outputs: Vec<(u32, String)>,
+    varyings: Vec<String>,
+    varying_mode: TransformFeedbackMode,
+    separable: bool,
+    binary_retrievable: bool,
+    validate: bool,
+    data: PhantomData<&'s ()>,
+}
+
+impl<'s> ProgramBuilder<'s> {
+    pub fn new() -> Self {
+        Self {
+            shaders: vec![],
+            attributes: vec![],
+            fragment_outputs: vec![],
+            varyings: vec![],
+            varying_mode: TransformFeedbackMode::Interleaved,
+            separable: false,
+            binary_retrievable: false,
+            validate: false,
+            data: PhantomData,
+        }
+    }
+
+    /// Attach a shader. The shader only has to live until the program is linked
+    /// and can be attached to several programs.
+    pub fn attach<S: AsShaderType>(mut self, shader: &'s Shader<S>) -> Self {
+        self.shaders.push(shader.handle);
+        self
+    }
+
+    /// Bind a vertex attribute to a location, like `layout (location = N)` would.
+    pub fn bind_attribute(mut self, location: u32, name: &str) -> Self {
+        self.attributes.push((location, name.to_owned()));
+        self
+    }
+
+    /// Bind a fragment shader output to a draw buffer, for rendering into several
+    /// color attachments at once.
+    pub fn bind_fragment_output(mut self, color_number: u32, name: &str) -> Self {
+        self.fragment_outputs.push((color_number, name.to_owned()));
+        self
+    }
+
+    /// Capture these outputs of the last vertex processing stage with transform feedback.
+    pub fn transform_feedback_varyings(
+        mut self,
+        varyings: &[&str],
+        mode: TransformFeedbackMode,
+    ) -> Self {
+        self.varyings = varyings.iter().map(|v| (*v).to_owned()).collect();
+        self.varying_mode = mode;
+        self
+    }
+
+    /// Link with `GL_PROGRAM_SEPARABLE`, see [`SeparableProgram`](crate::SeparableProgram).
+    pub fn separable(mut self, separable: bool) -> Self {
+        self.separable = separable;
+        self
+    }
+
+    /// Hint that the binary will be retrieved, see [`ProgramCache`](crate::ProgramCache).
+    pub fn binary_retrievable(mut self, retrievable: bool) -> Self {
+        self.binary_retrievable = retrievable;
+        self
+    }
+
+    /// Run `glValidateProgram` after linking and fail with its log.
+    /// The result depends on the current GL state, like the bound textures.
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    pub fn link(self) -> Result<Program, ShaderError> {
+        let to_cstring =
+            |s: &String| CString::new(s.as_str()).map_err(ShaderError::CStringConversion);
+
+        let attributes = self
+            .attributes
+            .iter()
+            .map(|(location, name)| Ok((*location, to_cstring(name)?)))
+            .collect::<Result<Vec<_>, ShaderError>>()?;
+        let fragment_outputs = self
+            .fragment_outputs
+            .iter()
+            .map(|(color, name)| Ok((*color, to_cstring(name)?)))
+            .collect::<Result<Vec<_>, ShaderError>>()?;
+        let varyings = self
+            .varyings
+            .iter()
+            .map(to_cstring)
+            .collect::<Result<Vec<_>, _>>()?;
+        let varying_pointers: Vec<*const c_char> = varyings.iter().map(|v| v.as_ptr()).collect();
+
+        let id = unsafe {
+            let id = gl::CreateProgram();
+
+            if self.separable {
+                gl::ProgramParameteri(id, gl::PROGRAM_SEPARABLE, gl::TRUE as i32);
+            }
+            if self.binary_retrievable {
+                gl::ProgramParameteri(id, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as i32);
+            }
+            for shader in &self.shaders {
+                gl::AttachShader(id, *shader);
+            }
+            for (location, name) in &attributes {
+                gl::BindAttribLocation(id, *location, name.as_ptr());
+            }
+            for (color, name) in &fragment_outputs {
+                gl::BindFragDataLocation(id, *color, name.as_ptr());
+            }
+            if !varyings.is_empty() {
+                gl::TransformFeedbackVaryings(
+                    id,
+                    varying_pointers.len() as i32,
+                    varying_pointers.as_ptr(),
+                    self.varying_mode as u32,
+                );
+            }
+
+            gl::LinkProgram(id);
+
+            // the shaders can be deleted as soon as they're dropped
+            for shader in &self.shaders {
+                gl::DetachShader(id, *shader);
+            }
+            id
+        };
+
+        if !Program::check_link_status(id) {
+            let err = Program::get_error(id);
+            unsafe { gl::DeleteProgram(id) };
+            return Err(err);
+        }
+
+        let program = Program::from_id(id);
+        if self.validate {
+            program.validate()?;
+        }
+
+        Ok(program)
+    }
+}
+
+impl Default for ProgramBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Program {
+    pub fn builder<'s>() -> ProgramBuilder<'s> {
+        ProgramBuilder::new()
+    }
+
+    /// Check whether the program can run with the current GL state with `glValidateProgram`.
+    pub fn validate(&self) -> Result<(), ShaderError> {
+        let mut status = 0;
+        unsafe {
+            gl::ValidateProgram(self.id);
+            gl::GetProgramiv(self.id, gl::VALIDATE_STATUS, ptr::addr_of_mut!(status));
+        }
+
+        if status != 0 {
+            return Ok(());
+        }
+
+        Err(ShaderError::ValidationError(ShaderLog::parse(
+            Self::info_log(self.id),
+            &[],
+        )))
+    }
+}
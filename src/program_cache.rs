@@ -0,0 +1,248 @@
+use crate::{PreprocessedSource, Program, Shader, ShaderError};
+use core::{ffi::c_void, ptr};
+use std::{
+    ffi::CStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Bumped whenever the entry layout changes, older entries are then cache misses.
+const MAGIC: &[u8; 4] = b"GLP2";
+
+impl Program {
+    /// Get the driver specific binary of the program and its format.
+    /// Returns `None` if the driver doesn't provide one.
+    pub fn binary(&self) -> Option<(u32, Vec<u8>)> {
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramiv(
+                self.id,
+                gl::PROGRAM_BINARY_LENGTH,
+                ptr::addr_of_mut!(length),
+            )
+        };
+        if length <= 0 {
+            return None;
+        }
+
+        let mut data = vec![0_u8; length as usize];
+        let mut written = 0;
+        let mut format = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                self.id,
+                length,
+                ptr::addr_of_mut!(written),
+                ptr::addr_of_mut!(format),
+                data.as_mut_ptr() as *mut c_void,
+            )
+        };
+        data.truncate(written.max(0) as usize);
+
+        (!data.is_empty()).then_some((format, data))
+    }
+
+    /// Load a binary retrieved with [`Program::binary`].
+    /// Fails with a linking error if the driver rejects it, e.g. after a driver update.
+    pub fn from_binary(format: u32, data: &[u8]) -> Result<Self, ShaderError> {
+        let id = unsafe {
+            let id = gl::CreateProgram();
+            gl::ProgramBinary(
+                id,
+                format,
+                data.as_ptr() as *const c_void,
+                data.len() as i32,
+            );
+            id
+        };
+
+        if !Self::check_link_status(id) {
+            let err = Self::get_error(id);
+            unsafe { gl::DeleteProgram(id) };
+            return Err(err);
+        }
+
+        Ok(Self::from_id(id))
+    }
+}
+
+/// Stores linked program binaries on disk so later runs can skip compilation.
+///
+/// Entries are keyed by the shader sources and the GL vendor, renderer and version
+/// strings. Files are named after a hash of the key and store the full key, so a
+/// collision is a cache miss instead of the wrong program. Binaries rejected by the
+/// driver are deleted and the program is compiled from source again. Since
+/// preprocessed sources contain the injected `#define`s, every permutation gets its
+/// own entry.
+///
+/// The cache is best effort: failing to write an entry is not an error.
+#[derive(Debug)]
+pub struct ProgramCache {
+    dir: PathBuf,
+    driver: String,
+    supported: bool,
+}
+
+impl ProgramCache {
+    /// Open (or create) a cache in `dir`. Needs a current GL context.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, ShaderError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| ShaderError::CacheIo(dir.clone(), e))?;
+
+        let driver = [gl::VENDOR, gl::RENDERER, gl::VERSION]
+            .map(gl_string)
+            .join("\n");
+
+        let mut formats = 0;
+        unsafe { gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, ptr::addr_of_mut!(formats)) };
+
+        Ok(Self {
+            dir,
+            driver,
+            supported: formats > 0,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Load the program from the cache or compile it and store the result.
+    pub fn link(&self, vertex: &str, fragment: &str) -> Result<Program, ShaderError> {
+        self.load_or_link(&[vertex, fragment], || {
+            Program::new_retrievable(Shader::new(vertex)?, Shader::new(fragment)?)
+        })
+    }
+
+    pub fn link_preprocessed(
+        &self,
+        vertex: &PreprocessedSource,
+        fragment: &PreprocessedSource,
+    ) -> Result<Program, ShaderError> {
+        self.load_or_link(&[&vertex.source, &fragment.source], || {
+            Program::new_retrievable(
+                Shader::from_preprocessed(vertex)?,
+                Shader::from_preprocessed(fragment)?,
+            )
+        })
+    }
+
+    /// Remove every entry in the cache.
+    pub fn clear(&self) -> Result<(), ShaderError> {
+        let entries =
+            fs::read_dir(&self.dir).map_err(|e| ShaderError::CacheIo(self.dir.clone(), e))?;
+        for entry in entries.flatten() {
+            if entry.path().extension().is_some_and(|e| e == "bin") {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_or_link(
+        &self,
+        sources: &[&str],
+        link: impl FnOnce() -> Result<Program, ShaderError>,
+    ) -> Result<Program, ShaderError> {
+        if !self.supported {
+            return link();
+        }
+
+        let key = self.entry_key(sources);
+        let path = self.entry_path(&key);
+        if let Some(program) = Self::load(&path, &key) {
+            return Ok(program);
+        }
+
+        let program = link()?;
+        Self::store(&path, &key, &program);
+        Ok(program)
+    }
+
+    /// Entries are `MAGIC`, the key length and key, the binary format and the binary.
+    fn load(path: &Path, key: &[u8]) -> Option<Program> {
+        let data = fs::read(path).ok()?;
+        let rest = data.strip_prefix(MAGIC)?;
+        let (key_length, rest) = rest.split_first_chunk::<8>()?;
+        let key_length = usize::try_from(u64::from_le_bytes(*key_length)).ok()?;
+        if rest.get(..key_length)? != key {
+            // another key with the same hash, it gets replaced after linking
+            return None;
+        }
+        let (format, binary) = rest[key_length..].split_first_chunk::<4>()?;
+
+        match Program::from_binary(u32::from_le_bytes(*format), binary) {
+            Ok(program) => Some(program),
+            Err(_) => {
+                // stale binary, most likely the driver got updated
+                let _ = fs::remove_file(path);
+                None
+            }
+        }
+    }
+
+    fn store(path: &Path, key: &[u8], program: &Program) {
+        let Some((format, binary)) = program.binary() else {
+            return;
+        };
+
+        let mut data = Vec::with_capacity(MAGIC.len() + 8 + key.len() + 4 + binary.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&(key.len() as u64).to_le_bytes());
+        data.extend_from_slice(key);
+        data.extend_from_slice(&format.to_le_bytes());
+        data.extend_from_slice(&binary);
+
+        // write to a temporary file first so a crash never leaves a truncated entry behind
+        let temporary = path.with_extension("tmp");
+        if fs::write(&temporary, data).is_ok() {
+            let _ = fs::rename(&temporary, path);
+        }
+    }
+
+    fn entry_key(&self, sources: &[&str]) -> Vec<u8> {
+        let mut key = self.driver.as_bytes().to_vec();
+        for source in sources {
+            // length prefix, so moving code between the stages changes the key
+            key.extend_from_slice(&(source.len() as u64).to_le_bytes());
+            key.extend_from_slice(source.as_bytes());
+        }
+        key
+    }
+
+    fn entry_path(&self, key: &[u8]) -> PathBuf {
+        let mut hash = Fnv1a::new();
+        hash.write(key);
+
+        self.dir.join(format!("{:016x}.bin", hash.0))
+    }
+}
+
+fn gl_string(name: u32) -> String {
+    let ptr = unsafe { gl::GetString(name) };
+    if ptr.is_null() {
+        return String::new();
+    }
+
+    unsafe { CStr::from_ptr(ptr as *const _) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 64-bit FNV-1a. `DefaultHasher` is not guaranteed to be stable between Rust
+/// releases, which would silently invalidate the cache.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
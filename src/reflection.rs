@@ -0,0 +1,175 @@
+use crate::Program;
+use core::ptr;
+use std::ffi::{CString, c_char};
+
+/// An input or output variable of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceVariable {
+    pub name: String,
+    /// The GL type enum, e.g. `gl::FLOAT_VEC2`.
+    pub gl_type: u32,
+    pub array_size: i32,
+    /// `-1` if the variable has no location, e.g. outputs of a vertex shader that
+    /// are only matched by name.
+    pub location: i32,
+}
+
+impl InterfaceVariable {
+    pub fn is_builtin(&self) -> bool {
+        self.name.starts_with("gl_")
+    }
+}
+
+/// An active uniform of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformInfo {
+    /// The name without the `[0]` suffix the driver reports for arrays.
+    pub name: String,
+    /// The GL type enum, e.g. `gl::FLOAT_MAT4`.
+    pub gl_type: u32,
+    pub array_size: i32,
+    /// `-1` for uniforms inside of a uniform block.
+    pub location: i32,
+}
+
+/// The inputs and outputs of a program, as reported by the driver.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramInterface {
+    pub inputs: Vec<InterfaceVariable>,
+    pub outputs: Vec<InterfaceVariable>,
+}
+
+impl Program {
+    /// Query the active inputs and outputs of the program.
+    ///
+    /// Needs `GL_ARB_program_interface_query` (core in 4.3).
+    /// Returns `None` if the driver doesn't support it.
+    pub fn interface(&self) -> Option<ProgramInterface> {
+        if !gl::GetProgramInterfaceiv::is_loaded() {
+            return None;
+        }
+
+        Some(ProgramInterface {
+            inputs: self.resources(gl::PROGRAM_INPUT),
+            outputs: self.resources(gl::PROGRAM_OUTPUT),
+        })
+    }
+
+    /// The location of the fragment output `name`, the draw buffer it writes to.
+    /// `None` if there's no active output of that name. Works on every GL 3.3 context.
+    pub fn output_location(&self, name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+        let location = unsafe { gl::GetFragDataLocation(self.id, name.as_ptr()) };
+        u32::try_from(location).ok()
+    }
+
+    /// Query the active uniforms of the program. Works on every GL 3.3 context.
+    pub fn uniforms(&self) -> Vec<UniformInfo> {
+        let mut count = 0;
+        let mut max_name_length = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORMS, ptr::addr_of_mut!(count));
+            gl::GetProgramiv(
+                self.id,
+                gl::ACTIVE_UNIFORM_MAX_LENGTH,
+                ptr::addr_of_mut!(max_name_length),
+            );
+        }
+
+        (0..count.max(0) as u32)
+            .map(|index| {
+                let mut name = vec![0_u8; max_name_length.max(1) as usize];
+                let mut length = 0;
+                let mut array_size = 0;
+                let mut gl_type = 0;
+
+                unsafe {
+                    gl::GetActiveUniform(
+                        self.id,
+                        index,
+                        name.len() as i32,
+                        ptr::addr_of_mut!(length),
+                        ptr::addr_of_mut!(array_size),
+                        ptr::addr_of_mut!(gl_type),
+                        name.as_mut_ptr() as *mut c_char,
+                    );
+                }
+                name.truncate(length.max(0) as usize);
+                name.push(0);
+
+                let location =
+                    unsafe { gl::GetUniformLocation(self.id, name.as_ptr() as *const c_char) };
+                name.pop();
+
+                let mut name = String::from_utf8_lossy(&name).into_owned();
+                if let Some(stripped) = name.strip_suffix("[0]") {
+                    name.truncate(stripped.len());
+                }
+
+                UniformInfo {
+                    name,
+                    gl_type,
+                    array_size,
+                    location,
+                }
+            })
+            .collect()
+    }
+
+    fn resources(&self, interface: u32) -> Vec<InterfaceVariable> {
+        let mut count = 0;
+        let mut max_name_length = 0;
+        unsafe {
+            gl::GetProgramInterfaceiv(
+                self.id,
+                interface,
+                gl::ACTIVE_RESOURCES,
+                ptr::addr_of_mut!(count),
+            );
+            gl::GetProgramInterfaceiv(
+                self.id,
+                interface,
+                gl::MAX_NAME_LENGTH,
+                ptr::addr_of_mut!(max_name_length),
+            );
+        }
+
+        let properties = [gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION];
+        (0..count.max(0) as u32)
+            .map(|index| {
+                let mut values = [0_i32; 3];
+                let mut name = vec![0_u8; max_name_length.max(1) as usize];
+                let mut length = 0;
+
+                unsafe {
+                    gl::GetProgramResourceiv(
+                        self.id,
+                        interface,
+                        index,
+                        properties.len() as i32,
+                        properties.as_ptr(),
+                        values.len() as i32,
+                        ptr::null_mut(),
+                        values.as_mut_ptr(),
+                    );
+                    gl::GetProgramResourceName(
+                        self.id,
+                        interface,
+                        index,
+                        name.len() as i32,
+                        ptr::addr_of_mut!(length),
+                        name.as_mut_ptr() as *mut c_char,
+                    );
+                }
+                name.truncate(length.max(0) as usize);
+
+                InterfaceVariable {
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    gl_type: values[0] as u32,
+                    array_size: values[1],
+                    location: values[2],
+                }
+            })
+            .collect()
+    }
+}
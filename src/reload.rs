@@ -0,0 +1,101 @@
+use crate::{Program, ShaderError};
+use core::ops;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_owned(),
+            modified: Self::modification_time(path),
+        }
+    }
+
+    fn modification_time(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Returns `true` if the file changed since the last call.
+    fn refresh(&mut self) -> bool {
+        let modified = Self::modification_time(&self.path);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+/// A [`Program`] built from shader files that gets recompiled when the files change.
+///
+/// Call [`ReloadableProgram::poll`] once per frame on the thread owning the GL context.
+/// If the new sources fail to compile, the last good program is kept and the error
+/// is passed to the callback.
+pub struct ReloadableProgram {
+    program: Program,
+    vertex: WatchedFile,
+    fragment: WatchedFile,
+    on_error: Box<dyn FnMut(&ShaderError)>,
+}
+
+impl ReloadableProgram {
+    pub fn new(
+        vertex_path: impl AsRef<Path>,
+        fragment_path: impl AsRef<Path>,
+        on_error: impl FnMut(&ShaderError) + 'static,
+    ) -> Result<Self, ShaderError> {
+        let vertex = WatchedFile::new(vertex_path.as_ref());
+        let fragment = WatchedFile::new(fragment_path.as_ref());
+        let program = Program::from_files(&vertex.path, &fragment.path)?;
+
+        Ok(Self {
+            program,
+            vertex,
+            fragment,
+            on_error: Box::new(on_error),
+        })
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Recompile the program if any of the source files changed.
+    /// Returns `true` if the program was replaced.
+    pub fn poll(&mut self) -> bool {
+        // both files have to be refreshed, so no short circuiting here
+        let changed = self.vertex.refresh() | self.fragment.refresh();
+        if !changed {
+            return false;
+        }
+
+        match self.reload() {
+            Ok(()) => true,
+            Err(err) => {
+                (self.on_error)(&err);
+                false
+            }
+        }
+    }
+
+    /// Recompile the program unconditionally.
+    /// On failure the previous program stays in use.
+    pub fn reload(&mut self) -> Result<(), ShaderError> {
+        self.program = Program::from_files(&self.vertex.path, &self.fragment.path)?;
+        Ok(())
+    }
+}
+
+impl ops::Deref for ReloadableProgram {
+    type Target = Program;
+
+    fn deref(&self) -> &Self::Target {
+        &self.program
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{Texture, TextureUnits};
+use core::{ffi::CStr, ptr};
+
+// core in GL 4.6, but missing from the generated bindings
+const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat = gl::REPEAT,
+    MirroredRepeat = gl::MIRRORED_REPEAT,
+    ClampToEdge = gl::CLAMP_TO_EDGE,
+    ClampToBorder = gl::CLAMP_TO_BORDER,
+}
+
+/// How a texture is sampled, applied with [`Texture::set_sampler`] or
+/// baked into a [`Sampler`].
+///
+/// ```ignore
+/// let pixel_art = SamplerDesc::nearest().wrap(Wrap::ClampToEdge);
+/// texture.set_sampler(&pixel_art);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerDesc {
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// `None` samples only the base level.
+    pub mipmap_filter: Option<Filter>,
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    pub wrap_r: Wrap,
+    /// Used with [`Wrap::ClampToBorder`].
+    pub border_color: [f32; 4],
+    /// `1.0` disables anisotropic filtering.
+    pub max_anisotropy: f32,
+    /// Added to the mip level picked by the GPU, negative values sharpen.
+    pub lod_bias: f32,
+}
+
+impl SamplerDesc {
+    /// The GL defaults: linear filtering between mip levels, repeating.
+    pub const fn new() -> Self {
+        Self {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            wrap_r: Wrap::Repeat,
+            border_color: [0.0; 4],
+            max_anisotropy: 1.0,
+            lod_bias: 0.0,
+        }
+    }
+
+    /// Nearest filtering, also between mip levels.
+    pub const fn nearest() -> Self {
+        Self::new()
+            .filter(Filter::Nearest)
+            .mipmap_filter(Some(Filter::Nearest))
+    }
+
+    /// Trilinear filtering.
+    pub const fn linear() -> Self {
+        Self::new()
+    }
+
+    /// Set the minification and magnification filter.
+    pub const fn filter(mut self, filter: Filter) -> Self {
+        self.min_filter = filter;
+        self.mag_filter = filter;
+        self
+    }
+
+    pub const fn min_filter(mut self, filter: Filter) -> Self {
+        self.min_filter = filter;
+        self
+    }
+
+    pub const fn mag_filter(mut self, filter: Filter) -> Self {
+        self.mag_filter = filter;
+        self
+    }
+
+    pub const fn mipmap_filter(mut self, filter: Option<Filter>) -> Self {
+        self.mipmap_filter = filter;
+        self
+    }
+
+    /// Set the wrap mode of every coordinate.
+    pub const fn wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self.wrap_r = wrap;
+        self
+    }
+
+    pub const fn wrap_s(mut self, wrap: Wrap) -> Self {
+        self.wrap_s = wrap;
+        self
+    }
+
+    pub const fn wrap_t(mut self, wrap: Wrap) -> Self {
+        self.wrap_t = wrap;
+        self
+    }
+
+    pub const fn wrap_r(mut self, wrap: Wrap) -> Self {
+        self.wrap_r = wrap;
+        self
+    }
+
+    pub const fn border_color(mut self, color: [f32; 4]) -> Self {
+        self.border_color = color;
+        self
+    }
+
+    /// Clamped to what the driver supports. Ignored without anisotropic filtering support.
+    pub const fn anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    pub const fn lod_bias(mut self, bias: f32) -> Self {
+        self.lod_bias = bias;
+        self
+    }
+
+    const fn gl_min_filter(&self) -> u32 {
+        match (self.min_filter, self.mipmap_filter) {
+            (Filter::Nearest, None) => gl::NEAREST,
+            (Filter::Linear, None) => gl::LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    const fn gl_mag_filter(&self) -> u32 {
+        match self.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+
+    /// Apply every parameter with `set_i` and `set_f`, shared between
+    /// `glTexParameter*` and `glSamplerParameter*`.
+    fn apply(&self, mut set_i: impl FnMut(u32, i32), mut set_f: impl FnMut(u32, *const f32)) {
+        set_i(gl::TEXTURE_MIN_FILTER, self.gl_min_filter() as i32);
+        set_i(gl::TEXTURE_MAG_FILTER, self.gl_mag_filter() as i32);
+        set_i(gl::TEXTURE_WRAP_S, self.wrap_s as i32);
+        set_i(gl::TEXTURE_WRAP_T, self.wrap_t as i32);
+        set_i(gl::TEXTURE_WRAP_R, self.wrap_r as i32);
+        set_f(gl::TEXTURE_BORDER_COLOR, self.border_color.as_ptr());
+        set_f(gl::TEXTURE_LOD_BIAS, ptr::addr_of!(self.lod_bias));
+
+        let max_supported = max_anisotropy();
+        if max_supported > 1.0 {
+            let anisotropy = self.max_anisotropy.clamp(1.0, max_supported);
+            set_f(TEXTURE_MAX_ANISOTROPY, ptr::addr_of!(anisotropy));
+        }
+    }
+}
+
+impl SamplerDesc {
+    /// Bind the texture to the active unit and set its sampling parameters.
+    pub(crate) fn apply_to_texture(&self, target: u32, id: u32) {
+        TextureUnits::bind_for_update(target, id);
+        self.apply(
+            |name, value| unsafe { gl::TexParameteri(target, name, value) },
+            |name, value| unsafe { gl::TexParameterfv(target, name, value) },
+        );
+    }
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The highest anisotropy the driver supports, `1.0` if anisotropic filtering
+/// isn't available. Cached with the state of the texture units of the context.
+fn max_anisotropy() -> f32 {
+    TextureUnits::max_anisotropy(query_max_anisotropy)
+}
+
+fn query_max_anisotropy() -> f32 {
+    if !anisotropy_supported() {
+        return 1.0;
+    }
+
+    let mut max = 1.0_f32;
+    unsafe { gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, ptr::addr_of_mut!(max)) };
+    max
+}
+
+/// Anisotropic filtering is core in GL 4.6, and an extension before that.
+fn anisotropy_supported() -> bool {
+    let (mut major, mut minor) = (0, 0);
+    let mut count = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, ptr::addr_of_mut!(major));
+        gl::GetIntegerv(gl::MINOR_VERSION, ptr::addr_of_mut!(minor));
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, ptr::addr_of_mut!(count));
+    }
+    if (major, minor) >= (4, 6) {
+        return true;
+    }
+
+    (0..count.max(0) as u32).any(|index| {
+        let name = unsafe { gl::GetStringi(gl::EXTENSIONS, index) };
+        !name.is_null()
+            && matches!(
+                unsafe { CStr::from_ptr(name as *const _) }.to_bytes(),
+                b"GL_EXT_texture_filter_anisotropic" | b"GL_ARB_texture_filter_anisotropic"
+            )
+    })
+}
+
+impl Texture {
+    /// Set how the texture is sampled when no [`Sampler`] is bound to its unit.
+    ///
+    /// Binds the texture to the active texture unit.
+    pub fn set_sampler(&self, desc: &SamplerDesc) {
+        desc.apply_to_texture(gl::TEXTURE_2D, self.id);
+    }
+}
+
+/// A sampler object, overriding the sampling state of any texture bound to the
+/// same unit. See [`ActiveTexture::with_sampler`](crate::ActiveTexture::with_sampler).
+pub struct Sampler {
+    pub(crate) id: u32,
+    desc: SamplerDesc,
+}
+
+impl Sampler {
+    pub fn new(desc: &SamplerDesc) -> Self {
+        let mut id = 0;
+        unsafe { gl::GenSamplers(1, ptr::addr_of_mut!(id)) };
+        desc.apply(
+            |name, value| unsafe { gl::SamplerParameteri(id, name, value) },
+            |name, value| unsafe { gl::SamplerParameterfv(id, name, value) },
+        );
+
+        Self { id, desc: *desc }
+    }
+
+    pub fn desc(&self) -> &SamplerDesc {
+        &self.desc
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        TextureUnits::forget_sampler(self.id);
+        unsafe { gl::DeleteSamplers(1, ptr::addr_of!(self.id)) }
+    }
+}
+
+impl std::fmt::Debug for Sampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sampler")
+            .field("id", &self.id)
+            .field("desc", &self.desc)
+            .finish()
+    }
+}
@@ -1,5 +1,16 @@
+use crate::{
+    PreprocessError, PreprocessedSource, ShaderLog, SourceFile, TextureUnitError, TextureUnits,
+    uniforms::LayoutKey,
+};
 use core::{error, fmt, marker::PhantomData, ptr};
-use std::ffi::{CStr, CString, NulError, c_char};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{CStr, CString, NulError, c_char},
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 #[repr(u32)]
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -28,6 +39,65 @@ impl AsShaderType for Vertex {
     }
 }
 
+/// GLSL source for a specific stage, usually created with the [`glsl!`](crate::glsl) macro
+/// which validates it at compile time.
+pub struct ShaderSource<S: AsShaderType> {
+    source: &'static str,
+    stage: PhantomData<S>,
+}
+
+impl<S: AsShaderType> ShaderSource<S> {
+    /// Wrap a source without validating it.
+    pub const fn new_unchecked(source: &'static str) -> Self {
+        Self {
+            source,
+            stage: PhantomData,
+        }
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        self.source
+    }
+}
+
+impl<S: AsShaderType> Clone for ShaderSource<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: AsShaderType> Copy for ShaderSource<S> {}
+
+impl<S: AsShaderType> fmt::Debug for ShaderSource<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ShaderSource").field(&self.source).finish()
+    }
+}
+
+/// Anything [`Shader::new`] can compile. Plain strings work for every stage,
+/// a [`ShaderSource`] only for the stage it was made for.
+pub trait AsShaderSource<S: AsShaderType> {
+    fn as_source(&self) -> &str;
+}
+
+impl<S: AsShaderType> AsShaderSource<S> for &str {
+    fn as_source(&self) -> &str {
+        self
+    }
+}
+
+impl<S: AsShaderType> AsShaderSource<S> for &String {
+    fn as_source(&self) -> &str {
+        self
+    }
+}
+
+impl<S: AsShaderType> AsShaderSource<S> for ShaderSource<S> {
+    fn as_source(&self) -> &str {
+        self.source
+    }
+}
+
 pub struct Shader<S: AsShaderType> {
     pub(crate) handle: u32,
     data: PhantomData<S>,
@@ -35,21 +105,45 @@ pub struct Shader<S: AsShaderType> {
 
 #[derive(Debug)]
 pub enum ShaderError {
-    CompilationError(String),
-    LinkingError(String),
+    CompilationError(ShaderLog),
+    LinkingError(ShaderLog),
     CStringConversion(NulError),
     UnknownUniformLocation(String),
+    FileRead(PathBuf, io::Error),
+    Preprocess(PreprocessError),
+    InterfaceMismatch(String),
+    ValidationError(ShaderLog),
+    UniformTypeMismatch(String),
+    /// The [`ProgramCache`](crate::ProgramCache) directory can't be created or read.
+    CacheIo(PathBuf, io::Error),
+    /// A texture uniform couldn't be bound to a unit.
+    TextureUnit(TextureUnitError),
 }
 
 impl fmt::Display for ShaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::CompilationError(err) => write!(f, "Cannot compile the shader: {err}"),
+            Self::CompilationError(log) => write!(f, "Cannot compile the shader:\n{log}"),
             Self::CStringConversion(err) => {
                 write!(f, "Cannot convert string to a C pointer: {err}")
             }
-            Self::LinkingError(err) => write!(f, "Cannot link the shaders: {err}"),
+            Self::LinkingError(log) => write!(f, "Cannot link the shaders:\n{log}"),
             Self::UnknownUniformLocation(s) => write!(f, "Unknown uniform location: {s}"),
+            Self::FileRead(path, err) => {
+                write!(f, "Cannot read the shader file {}: {err}", path.display())
+            }
+            Self::Preprocess(err) => write!(f, "Cannot preprocess the shader: {err}"),
+            Self::InterfaceMismatch(s) => write!(f, "Shader interfaces don't match: {s}"),
+            Self::ValidationError(log) => write!(f, "Validation failed:\n{log}"),
+            Self::UniformTypeMismatch(s) => write!(f, "Uniform has a different type: {s}"),
+            Self::CacheIo(path, err) => {
+                write!(
+                    f,
+                    "Cannot access the program cache {}: {err}",
+                    path.display()
+                )
+            }
+            Self::TextureUnit(err) => write!(f, "Cannot bind the texture: {err}"),
         }
     }
 }
@@ -57,12 +151,39 @@ impl fmt::Display for ShaderError {
 impl error::Error for ShaderError {}
 
 impl<S: AsShaderType> Shader<S> {
-    pub fn new(source: &str) -> Result<Self, ShaderError> {
-        let source = CString::new(source).map_err(ShaderError::CStringConversion)?;
-        Self::from_cstr(&source)
+    pub fn new(source: impl AsShaderSource<S>) -> Result<Self, ShaderError> {
+        let source = source.as_source();
+        let cstr = CString::new(source).map_err(ShaderError::CStringConversion)?;
+        Self::compile(&cstr, || vec![SourceFile::inline(source)])
+    }
+
+    /// Read the shader source from a file and compile it.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ShaderError> {
+        let path = path.as_ref();
+        let source =
+            fs::read_to_string(path).map_err(|e| ShaderError::FileRead(path.to_owned(), e))?;
+        let cstr = CString::new(source.as_str()).map_err(ShaderError::CStringConversion)?;
+
+        Self::compile(&cstr, || {
+            vec![SourceFile {
+                name: path.display().to_string(),
+                source,
+            }]
+        })
+    }
+
+    /// Compile the output of the [`Preprocessor`](crate::Preprocessor), mapping
+    /// errors back to the included files.
+    pub fn from_preprocessed(source: &PreprocessedSource) -> Result<Self, ShaderError> {
+        let cstr = CString::new(source.source.as_str()).map_err(ShaderError::CStringConversion)?;
+        Self::compile(&cstr, || source.files.clone())
     }
 
     pub fn from_cstr(cstr: &CStr) -> Result<Self, ShaderError> {
+        Self::compile(cstr, || vec![SourceFile::inline(&cstr.to_string_lossy())])
+    }
+
+    fn compile(cstr: &CStr, files: impl FnOnce() -> Vec<SourceFile>) -> Result<Self, ShaderError> {
         unsafe {
             let ptr = cstr.as_ptr();
             let id = gl::CreateShader(S::as_shader_type() as u32);
@@ -70,7 +191,9 @@ impl<S: AsShaderType> Shader<S> {
             gl::CompileShader(id);
 
             if !Self::check_compile_status(id) {
-                return Err(Self::get_error(id));
+                let log = ShaderLog::parse(Self::get_info_log(id), &files());
+                gl::DeleteShader(id);
+                return Err(ShaderError::CompilationError(log));
             }
 
             Ok(Self {
@@ -80,15 +203,23 @@ impl<S: AsShaderType> Shader<S> {
         }
     }
 
-    fn get_error(id: u32) -> ShaderError {
-        let mut buffer = [c_char::MIN; 512];
-        unsafe { gl::GetShaderInfoLog(id, 512, ptr::null_mut(), buffer.as_mut_ptr()) };
+    fn get_info_log(id: u32) -> String {
+        let mut length = 0;
+        unsafe { gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, ptr::addr_of_mut!(length)) };
 
-        let err = unsafe { CStr::from_ptr(buffer.as_ptr()) }
-            .to_string_lossy()
-            .to_string();
+        let mut buffer = vec![0_u8; length.max(1) as usize];
+        let mut written = 0;
+        unsafe {
+            gl::GetShaderInfoLog(
+                id,
+                buffer.len() as i32,
+                ptr::addr_of_mut!(written),
+                buffer.as_mut_ptr() as *mut c_char,
+            )
+        };
+        buffer.truncate(written.max(0) as usize);
 
-        ShaderError::CompilationError(err)
+        String::from_utf8_lossy(&buffer).into_owned()
     }
 
     fn check_compile_status(id: u32) -> bool {
@@ -106,48 +237,75 @@ impl<S: AsShaderType> Drop for Shader<S> {
 }
 
 pub struct Program {
-    id: u32,
+    pub(crate) id: u32,
+    /// Uniform locations of every [`Uniforms`](crate::Uniforms) type used with this program.
+    pub(crate) uniform_layouts: RefCell<HashMap<LayoutKey, Rc<[i32]>>>,
 }
 
 impl Program {
+    pub(crate) fn from_id(id: u32) -> Self {
+        Self {
+            id,
+            uniform_layouts: RefCell::default(),
+        }
+    }
+
     pub fn new(
         vertex_shader: Shader<Vertex>,
         fragment_shader: Shader<Fragment>,
     ) -> Result<Self, ShaderError> {
-        Self::link_internal(vertex_shader, fragment_shader).map(|id| Self { id })
+        Self::builder()
+            .attach(&vertex_shader)
+            .attach(&fragment_shader)
+            .link()
     }
 
-    fn link_internal(
+    /// Link a program whose binary can be retrieved afterwards, see [`ProgramCache`](crate::ProgramCache).
+    pub fn new_retrievable(
         vertex_shader: Shader<Vertex>,
         fragment_shader: Shader<Fragment>,
-    ) -> Result<u32, ShaderError> {
-        let id = unsafe {
-            let id = gl::CreateProgram();
-            gl::AttachShader(id, vertex_shader.handle);
-            gl::AttachShader(id, fragment_shader.handle);
-            gl::LinkProgram(id);
-            id
-        };
+    ) -> Result<Self, ShaderError> {
+        Self::builder()
+            .attach(&vertex_shader)
+            .attach(&fragment_shader)
+            .binary_retrievable(true)
+            .link()
+    }
 
-        if !Self::check_link_status(id) {
-            return Err(Self::get_error(id));
-        }
+    /// Compile and link a program from shader sources stored on disk.
+    pub fn from_files(
+        vertex_path: impl AsRef<Path>,
+        fragment_path: impl AsRef<Path>,
+    ) -> Result<Self, ShaderError> {
+        let vertex_shader = Shader::from_file(vertex_path)?;
+        let fragment_shader = Shader::from_file(fragment_path)?;
+        Self::new(vertex_shader, fragment_shader)
+    }
 
-        Ok(id)
+    pub(crate) fn get_error(id: u32) -> ShaderError {
+        ShaderError::LinkingError(ShaderLog::parse(Self::info_log(id), &[]))
     }
 
-    fn get_error(id: u32) -> ShaderError {
-        let mut buffer = [c_char::MIN; 512];
-        unsafe { gl::GetProgramInfoLog(id, 512, ptr::null_mut(), buffer.as_mut_ptr()) };
+    pub(crate) fn info_log(id: u32) -> String {
+        let mut length = 0;
+        unsafe { gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, ptr::addr_of_mut!(length)) };
 
-        let err = unsafe { CStr::from_ptr(buffer.as_ptr()) }
-            .to_string_lossy()
-            .to_string();
+        let mut buffer = vec![0_u8; length.max(1) as usize];
+        let mut written = 0;
+        unsafe {
+            gl::GetProgramInfoLog(
+                id,
+                buffer.len() as i32,
+                ptr::addr_of_mut!(written),
+                buffer.as_mut_ptr() as *mut c_char,
+            )
+        };
+        buffer.truncate(written.max(0) as usize);
 
-        ShaderError::LinkingError(err)
+        String::from_utf8_lossy(&buffer).into_owned()
     }
 
-    fn check_link_status(id: u32) -> bool {
+    pub(crate) fn check_link_status(id: u32) -> bool {
         let mut success = 1;
         unsafe { gl::GetProgramiv(id, gl::LINK_STATUS, ptr::addr_of_mut!(success)) };
 
@@ -176,9 +334,8 @@ impl Program {
         }
 
         self.use_internal();
-        unsafe { uniform.put_uniform(uniform_position) };
-
-        Ok(())
+        TextureUnits::begin_uniform(self.id, uniform_position);
+        unsafe { uniform.put_uniform(uniform_position) }
     }
 }
 
@@ -225,17 +382,59 @@ pub fn setup_attribute(
 }
 
 pub trait Uniform {
-    unsafe fn put_uniform(&self, pos: i32);
+    /// # Safety
+    /// `pos` must be a valid uniform location of the program that is currently in use.
+    unsafe fn put_uniform(&self, pos: i32) -> Result<(), ShaderError>;
+
+    /// Whether a uniform declared with `gl_type` (e.g. `gl::FLOAT_MAT4`) can be set from this type.
+    /// Used to validate [`Uniforms`](crate::Uniforms) against the program.
+    fn accepts_gl_type(_gl_type: u32) -> bool
+    where
+        Self: Sized,
+    {
+        true
+    }
+}
+
+impl<U: Uniform> Uniform for &U {
+    unsafe fn put_uniform(&self, pos: i32) -> Result<(), ShaderError> {
+        unsafe { (*self).put_uniform(pos) }
+    }
+
+    fn accepts_gl_type(gl_type: u32) -> bool {
+        U::accepts_gl_type(gl_type)
+    }
 }
 
 impl Uniform for f32 {
-    unsafe fn put_uniform(&self, pos: i32) {
-        unsafe { gl::Uniform1f(pos, *self) }
+    unsafe fn put_uniform(&self, pos: i32) -> Result<(), ShaderError> {
+        unsafe { gl::Uniform1f(pos, *self) };
+        Ok(())
+    }
+
+    fn accepts_gl_type(gl_type: u32) -> bool {
+        gl_type == gl::FLOAT
+    }
+}
+
+impl Uniform for i32 {
+    unsafe fn put_uniform(&self, pos: i32) -> Result<(), ShaderError> {
+        unsafe { gl::Uniform1i(pos, *self) };
+        Ok(())
+    }
+
+    fn accepts_gl_type(gl_type: u32) -> bool {
+        gl_type == gl::INT
     }
 }
 
 impl Uniform for nalgebra_glm::Mat4 {
-    unsafe fn put_uniform(&self, pos: i32) {
-        unsafe { gl::UniformMatrix4fv(pos, 1, gl::FALSE, self.as_ptr()) }
+    unsafe fn put_uniform(&self, pos: i32) -> Result<(), ShaderError> {
+        unsafe { gl::UniformMatrix4fv(pos, 1, gl::FALSE, self.as_ptr()) };
+        Ok(())
+    }
+
+    fn accepts_gl_type(gl_type: u32) -> bool {
+        gl_type == gl::FLOAT_MAT4
     }
 }
@@ -0,0 +1,124 @@
+use crate::{
+    ActiveTexture, AttributeType, Buffer, Camera, DrawMode, DrawTarget, DrawUsage, Fragment,
+    Program, Shader, ShaderError, ShaderSource, Uniforms, Vao, Vertex, glsl, setup_attribute,
+};
+use nalgebra_glm::{self as glm, Mat4};
+use std::ptr;
+
+#[derive(Uniforms)]
+struct SkyboxUniforms<'t, 'a> {
+    view_projection: Mat4,
+    skybox: &'t ActiveTexture<'a>,
+}
+
+/// Draws a [`CubemapTexture`](crate::CubemapTexture) behind everything else.
+///
+/// Render it after the opaque geometry, it only covers pixels nothing has been drawn to.
+pub struct Skybox<'a> {
+    texture: ActiveTexture<'a>,
+    shader: Program,
+    vao: Vao,
+    _vbo: Buffer,
+}
+
+const VERTEX_SOURCE: ShaderSource<Vertex> = glsl!(
+    vertex,
+    r#"
+#version 330 core
+layout (location = 0) in vec3 position;
+out vec3 direction;
+
+uniform mat4 view_projection;
+
+void main() {
+    direction = position;
+    // w as z puts every vertex on the far plane
+    gl_Position = (view_projection * vec4(position, 1.0)).xyww;
+}
+"#
+);
+
+const FRAGMENT_SOURCE: ShaderSource<Fragment> = glsl!(
+    fragment,
+    r#"
+#version 330 core
+in vec3 direction;
+out vec4 color;
+
+uniform samplerCube skybox;
+
+void main() {
+    color = texture(skybox, direction);
+}
+"#
+);
+
+#[rustfmt::skip]
+const CUBE_VERTICES: [f32; 108] = [
+    -1.0,  1.0, -1.0,  -1.0, -1.0, -1.0,   1.0, -1.0, -1.0,
+     1.0, -1.0, -1.0,   1.0,  1.0, -1.0,  -1.0,  1.0, -1.0,
+
+    -1.0, -1.0,  1.0,  -1.0, -1.0, -1.0,  -1.0,  1.0, -1.0,
+    -1.0,  1.0, -1.0,  -1.0,  1.0,  1.0,  -1.0, -1.0,  1.0,
+
+     1.0, -1.0, -1.0,   1.0, -1.0,  1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,   1.0,  1.0, -1.0,   1.0, -1.0, -1.0,
+
+    -1.0, -1.0,  1.0,  -1.0,  1.0,  1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,   1.0, -1.0,  1.0,  -1.0, -1.0,  1.0,
+
+    -1.0,  1.0, -1.0,   1.0,  1.0, -1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,  -1.0,  1.0,  1.0,  -1.0,  1.0, -1.0,
+
+    -1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0, -1.0,
+     1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0,  1.0,
+];
+
+impl<'a> Skybox<'a> {
+    /// `texture` has to have a [`CubemapTexture`](crate::CubemapTexture) bound.
+    pub fn new(texture: ActiveTexture<'a>) -> Result<Self, ShaderError> {
+        let fragment_shader = Shader::new(FRAGMENT_SOURCE)?;
+        let vertex_shader = Shader::new(VERTEX_SOURCE)?;
+        let shader = Program::new(vertex_shader, fragment_shader)?;
+
+        let vao = Vao::new();
+        vao.bind();
+        let vbo = Buffer::new(DrawTarget::Array);
+        vbo.bind();
+        vbo.data(&CUBE_VERTICES, DrawUsage::StaticDraw);
+        setup_attribute(0, 3, 0, 0, AttributeType::f32);
+
+        Ok(Self {
+            texture,
+            shader,
+            vao,
+            _vbo: vbo,
+        })
+    }
+
+    /// Draw the skybox around `camera`. Only its rotation matters, the skybox
+    /// never gets closer.
+    pub fn render(&self, camera: &Camera) {
+        // dropping the translation keeps the camera in the middle of the cube
+        let view = glm::mat3_to_mat4(&glm::mat4_to_mat3(&camera.calculate_view()));
+        let view_projection = camera.calculate_projection() * view;
+
+        self.shader
+            .set_uniforms(&SkyboxUniforms {
+                view_projection,
+                skybox: &self.texture,
+            })
+            .expect("Should not fail");
+
+        let mut depth_func = 0;
+        unsafe {
+            gl::GetIntegerv(gl::DEPTH_FUNC, ptr::addr_of_mut!(depth_func));
+            // the depth buffer is cleared to the far plane, which `LESS` would reject
+            gl::DepthFunc(gl::LEQUAL);
+        }
+
+        self.vao.draw_arrays(DrawMode::Triangles, 0, 36);
+
+        unsafe { gl::DepthFunc(depth_func as u32) };
+    }
+}
@@ -1,19 +1,53 @@
 use crate::{
-    ActiveTexture, AttributeType, Buffer, Program, Shader, ShaderError, Vao, setup_attribute,
+    ActiveTexture, AtlasRegion, AttributeType, Buffer, Fragment, Program, Shader, ShaderError,
+    ShaderSource, Uniforms, Vao, Vertex, glsl, setup_attribute,
 };
 use nalgebra_glm::Mat4;
-use std::sync::OnceLock;
+use std::cell::RefCell;
 
-// vao, vbo, ebo
-static SPRITE_DATA: OnceLock<(Vao, Buffer, Buffer, Buffer)> = OnceLock::new();
+// vao, vbo, ebo, uv buffer
+type SpriteData = (Vao, Buffer, Buffer, Buffer);
+
+thread_local! {
+    // vertex arrays aren't shared between contexts, so every thread with a context gets its own
+    static SPRITE_DATA: RefCell<Option<SpriteData>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with the quad buffers of this thread, creating them on first use.
+fn with_sprite_data<R>(f: impl FnOnce(&SpriteData) -> R) -> R {
+    SPRITE_DATA.with_borrow_mut(|data| f(data.get_or_insert_with(Sprite::initialize_sprite_buffer)))
+}
+
+/// Delete the quad buffers of this thread, before their context is destroyed.
+#[cfg(feature = "headless")]
+pub(crate) fn release_sprite_data() {
+    drop(SPRITE_DATA.with_borrow_mut(Option::take));
+}
+
+#[derive(Uniforms)]
+struct SpriteUniforms<'t, 'a> {
+    mvp: Mat4,
+    sprite: &'t ActiveTexture<'a>,
+}
+
+#[derive(Uniforms)]
+struct LayeredSpriteUniforms<'t, 'a> {
+    mvp: Mat4,
+    sprite: &'t ActiveTexture<'a>,
+    layer: i32,
+}
 
 pub struct Sprite<'a> {
     texture: ActiveTexture<'a>,
     shader: Program,
     texture_size: (f32, f32),
+    /// The texture coordinates of the bottom left and top right corner.
+    uv: [f32; 4],
 }
 
-const VERTEX_SOURCE: &str = r#"
+const VERTEX_SOURCE: ShaderSource<Vertex> = glsl!(
+    vertex,
+    r#"
 #version 330 core
 layout (location = 0) in vec2 position;
 layout (location = 1) in vec2 uv;
@@ -26,9 +60,12 @@ void main() {
     gl_Position.z = 0.0;
     tex_uv = uv;
 }
-"#;
+"#
+);
 
-const FRAGMENT_SOURCE: &str = r#"
+const FRAGMENT_SOURCE: ShaderSource<Fragment> = glsl!(
+    fragment,
+    r#"
 #version 330 core
 in vec2 tex_uv;
 out vec4 color;
@@ -38,7 +75,24 @@ uniform sampler2D sprite;
 void main() {
     color = texture(sprite, tex_uv);
 }
-"#;
+"#
+);
+
+const LAYERED_FRAGMENT_SOURCE: ShaderSource<Fragment> = glsl!(
+    fragment,
+    r#"
+#version 330 core
+in vec2 tex_uv;
+out vec4 color;
+
+uniform sampler2DArray sprite;
+uniform int layer;
+
+void main() {
+    color = texture(sprite, vec3(tex_uv, float(layer)));
+}
+"#
+);
 
 impl<'a> Sprite<'a> {
     pub fn new(texture: ActiveTexture<'a>, texture_size: (u32, u32)) -> Result<Self, ShaderError> {
@@ -47,17 +101,47 @@ impl<'a> Sprite<'a> {
         let shader = Program::new(vertex_shader, fragment_shader)?;
 
         shader.use_internal();
-        SPRITE_DATA.get_or_init(Self::initialize_sprite_buffer);
+        with_sprite_data(|_| ());
 
         Ok(Self {
             texture,
             shader,
             texture_size: (texture_size.0 as f32, texture_size.1 as f32),
+            uv: FULL_UV,
         })
     }
 
+    /// A sprite showing one region of a [`TextureAtlas`](crate::TextureAtlas).
+    /// `texture` has to have the page of the region bound.
+    pub fn from_region(
+        texture: ActiveTexture<'a>,
+        region: &AtlasRegion,
+    ) -> Result<Self, ShaderError> {
+        let mut sprite = Self::new(texture, (region.width, region.height))?;
+        sprite.uv = [
+            region.uv_min[0],
+            region.uv_min[1],
+            region.uv_max[0],
+            region.uv_max[1],
+        ];
+
+        Ok(sprite)
+    }
+
     pub fn render(&self, position: (f32, f32), transform: Mat4, scale: f32) {
-        let verts = [
+        self.shader.use_internal();
+        draw_quad(self.quad(position, scale), self.uv, || {
+            self.shader
+                .set_uniforms(&SpriteUniforms {
+                    mvp: transform,
+                    sprite: &self.texture,
+                })
+                .expect("Should not fail");
+        });
+    }
+
+    fn quad(&self, position: (f32, f32), scale: f32) -> [f32; 8] {
+        [
             position.0,
             position.1,
             position.0 + self.texture_size.0 * scale,
@@ -66,25 +150,7 @@ impl<'a> Sprite<'a> {
             position.1 + self.texture_size.1 * scale,
             position.0,
             position.1 + self.texture_size.1 * scale,
-        ];
-
-        self.shader.use_internal();
-        let sprite_data = SPRITE_DATA.get_or_init(Self::initialize_sprite_buffer);
-        sprite_data.0.bind();
-        sprite_data.1.bind();
-        sprite_data.1.subdata(0, &verts);
-
-        self.shader
-            .put_uniform("mvp", &transform)
-            .expect("Should not fail");
-        self.shader
-            .put_uniform("sprite", &self.texture)
-            .expect("Should not fail");
-
-        sprite_data.2.bind();
-        sprite_data
-            .0
-            .draw_elements(crate::DrawMode::Triangles, 6, AttributeType::u32);
+        ]
     }
 
     fn initialize_sprite_buffer() -> (Vao, Buffer, Buffer, Buffer) {
@@ -101,7 +167,7 @@ impl<'a> Sprite<'a> {
         let texture_buffer = Buffer::new(crate::DrawTarget::Array);
         texture_buffer.bind();
         let tex: [f32; 8] = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0];
-        texture_buffer.data(&tex, crate::DrawUsage::StreamDraw);
+        texture_buffer.data(&tex, crate::DrawUsage::DynamicDraw);
         setup_attribute(1, 2, 0, 0, crate::AttributeType::f32);
 
         let ebo = Buffer::new(crate::DrawTarget::ElementArray);
@@ -111,3 +177,69 @@ impl<'a> Sprite<'a> {
         (vao, vbo, ebo, texture_buffer)
     }
 }
+
+const FULL_UV: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+/// Upload the corners of the quad and draw it, with `set_uniforms` called in between.
+fn draw_quad(verts: [f32; 8], uv: [f32; 4], set_uniforms: impl FnOnce()) {
+    with_sprite_data(|sprite_data| {
+        sprite_data.0.bind();
+        sprite_data.1.bind();
+        sprite_data.1.subdata(0, &verts);
+        // the texture coordinates are shared between every sprite
+        let [u0, v0, u1, v1] = uv;
+        sprite_data.3.bind();
+        sprite_data.3.subdata(0, &[u0, v0, u1, v0, u1, v1, u0, v1]);
+
+        set_uniforms();
+
+        sprite_data.2.bind();
+        sprite_data
+            .0
+            .draw_elements(crate::DrawMode::Triangles, 6, AttributeType::u32);
+    });
+}
+
+/// A sprite drawn from one layer of a [`Texture2DArray`](crate::Texture2DArray),
+/// e.g. one frame of an animation. Layers don't bleed into each other like
+/// the cells of a [`SpriteSheet`](crate::SpriteSheet).
+pub struct LayeredSprite<'a> {
+    sprite: Sprite<'a>,
+}
+
+impl<'a> LayeredSprite<'a> {
+    /// `texture` has to have a [`Texture2DArray`](crate::Texture2DArray) bound,
+    /// `layer_size` is the size of one layer in pixels.
+    pub fn new(texture: ActiveTexture<'a>, layer_size: (u32, u32)) -> Result<Self, ShaderError> {
+        let fragment_shader = Shader::new(LAYERED_FRAGMENT_SOURCE)?;
+        let vertex_shader = Shader::new(VERTEX_SOURCE)?;
+        let shader = Program::new(vertex_shader, fragment_shader)?;
+
+        shader.use_internal();
+        with_sprite_data(|_| ());
+
+        Ok(Self {
+            sprite: Sprite {
+                texture,
+                shader,
+                texture_size: (layer_size.0 as f32, layer_size.1 as f32),
+                uv: FULL_UV,
+            },
+        })
+    }
+
+    pub fn render(&self, position: (f32, f32), transform: Mat4, scale: f32, layer: u32) {
+        let sprite = &self.sprite;
+        sprite.shader.use_internal();
+        draw_quad(sprite.quad(position, scale), sprite.uv, || {
+            sprite
+                .shader
+                .set_uniforms(&LayeredSpriteUniforms {
+                    mvp: transform,
+                    sprite: &sprite.texture,
+                    layer: layer as i32,
+                })
+                .expect("Should not fail");
+        });
+    }
+}
@@ -1,6 +1,15 @@
-use crate::{ActiveTexture, Buffer, Program, Shader, ShaderError, Vao, setup_attribute};
+use crate::{
+    ActiveTexture, AtlasRegion, Buffer, Fragment, Program, Shader, ShaderError, ShaderSource,
+    Uniforms, Vao, Vertex, glsl, setup_attribute,
+};
 use nalgebra_glm::Mat4;
 
+#[derive(Uniforms)]
+struct SpriteSheetUniforms<'t, 'a> {
+    tex: &'t ActiveTexture<'a>,
+    mvp: Mat4,
+}
+
 pub struct SpriteSheet<'a> {
     texture: ActiveTexture<'a>,
 
@@ -19,7 +28,9 @@ pub struct SpriteSheet<'a> {
 }
 
 impl<'a> SpriteSheet<'a> {
-    const VERTEX_SHADER: &'static str = "
+    const VERTEX_SHADER: ShaderSource<Vertex> = glsl!(
+        vertex,
+        "
     #version 330 core
 
     layout (location = 0) in vec2 position;
@@ -32,8 +43,11 @@ impl<'a> SpriteSheet<'a> {
         gl_Position.z = 0.0;
         frag_tex_coords = vec2(tex_coords);
     }
-    ";
-    const FRAGMENT_SHADER: &'static str = "
+    "
+    );
+    const FRAGMENT_SHADER: ShaderSource<Fragment> = glsl!(
+        fragment,
+        "
     #version 330 core
     in vec2 frag_tex_coords;
     out vec4 color;
@@ -43,7 +57,8 @@ impl<'a> SpriteSheet<'a> {
     void main() {
         color = texelFetch(tex, ivec2(frag_tex_coords), 0);
     }
-    ";
+    "
+    );
 
     pub fn new(
         texture: ActiveTexture<'a>,
@@ -60,12 +75,12 @@ impl<'a> SpriteSheet<'a> {
 
         let buffer_verts = Buffer::new(crate::DrawTarget::Array);
         buffer_verts.bind();
-        buffer_verts.data_empty(8 * size_of::<f32>(), crate::DrawUsage::DynamicDraw);
+        buffer_verts.data_empty(size_of::<[f32; 8]>(), crate::DrawUsage::DynamicDraw);
         setup_attribute(0, 2, 0, 0, crate::AttributeType::f32);
 
         let buffer_tex = Buffer::new(crate::DrawTarget::Array);
         buffer_tex.bind();
-        buffer_tex.data_empty(8 * size_of::<i32>(), crate::DrawUsage::DynamicDraw);
+        buffer_tex.data_empty(size_of::<[i32; 8]>(), crate::DrawUsage::DynamicDraw);
         setup_attribute(1, 2, 0, 0, crate::AttributeType::i32);
 
         let ebo = Buffer::new(crate::DrawTarget::ElementArray);
@@ -113,9 +128,6 @@ impl<'a> SpriteSheet<'a> {
         glDrawArrays(GL_TRI_STRIP, 0, 4);
          */
 
-        self.vao.bind();
-        self.program.use_internal();
-
         let verts = [
             position.0,
             position.1,
@@ -127,9 +139,6 @@ impl<'a> SpriteSheet<'a> {
             position.1 + self.symbol_h * scale,
         ];
 
-        self.buffer_verts.bind();
-        self.buffer_verts.subdata(0, &verts);
-
         let num_per_row = self.texture_w / self.symbol_w;
 
         let tx = nth % num_per_row as u8 * self.symbol_w as u8;
@@ -148,15 +157,44 @@ impl<'a> SpriteSheet<'a> {
             ty as i32 + self.symbol_h as i32,
         ];
 
-        self.buffer_tex.bind();
-        self.buffer_tex.subdata(0, &tex_verts);
+        self.draw_quad(&verts, &tex_verts, mvp);
+    }
 
-        self.program
-            .put_uniform("tex", &self.texture)
-            .expect("Should not fail");
+    /// Draw a region of a [`TextureAtlas`](crate::TextureAtlas) page instead of a
+    /// grid cell, e.g. a glyph of a packed font. The bound texture has to be the page
+    /// of the region.
+    pub fn draw_region(&self, position: (f32, f32), region: &AtlasRegion, mvp: Mat4, scale: f32) {
+        let (width, height) = (region.width as f32 * scale, region.height as f32 * scale);
+        let verts = [
+            position.0,
+            position.1,
+            position.0 + width,
+            position.1,
+            position.0 + width,
+            position.1 + height,
+            position.0,
+            position.1 + height,
+        ];
+
+        let (x, y) = (region.x as i32, region.y as i32);
+        let (w, h) = (region.width as i32, region.height as i32);
+        self.draw_quad(&verts, &[x, y, x + w, y, x + w, y + h, x, y + h], mvp);
+    }
+
+    fn draw_quad(&self, verts: &[f32; 8], tex_verts: &[i32; 8], mvp: Mat4) {
+        self.vao.bind();
+        self.program.use_internal();
+
+        self.buffer_verts.bind();
+        self.buffer_verts.subdata(0, verts);
+        self.buffer_tex.bind();
+        self.buffer_tex.subdata(0, tex_verts);
 
         self.program
-            .put_uniform("mvp", &mvp)
+            .set_uniforms(&SpriteSheetUniforms {
+                tex: &self.texture,
+                mvp,
+            })
             .expect("Should not fail");
 
         unsafe {
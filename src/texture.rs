@@ -1,35 +1,512 @@
-use crate::Uniform;
+use crate::{
+    Sampler, SamplerDesc, ShaderError, TextureFormat, TextureUnitError, TextureUnits, Uniform,
+};
+use core::{error, fmt};
 use std::{ffi::c_void, fmt::Debug, ptr};
 
-pub struct Texture(u32);
+#[derive(Debug)]
+pub enum TextureError {
+    InvalidSize {
+        width: i32,
+        height: i32,
+    },
+    DataSizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    RegionOutOfBounds {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    /// The operation doesn't support the pixel type of the format.
+    UnsupportedFormat(TextureFormat),
+    /// The layer of an array texture, or the slice of a 3D texture, doesn't exist.
+    InvalidLayer(i32),
+    /// An array texture was created from an empty list of layers.
+    NoLayers,
+    /// The mip level doesn't exist.
+    InvalidLevel(i32),
+    /// The pixel type of the format can't be read as the requested component type.
+    PixelTypeMismatch {
+        format: TextureFormat,
+        requested: &'static str,
+    },
+    /// The alignment isn't 1, 2, 4 or 8, or the rows are shorter than the region.
+    InvalidUnpackLayout(UnpackLayout),
+    /// The sample count is 0 or above what the format supports.
+    InvalidSampleCount {
+        samples: i32,
+        max: i32,
+    },
+    /// The resolve target doesn't have the size and format of the multisampled image.
+    ResolveMismatch {
+        format: TextureFormat,
+        width: i32,
+        height: i32,
+    },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize { width, height } => {
+                write!(f, "Invalid texture size: {width}x{height}")
+            }
+            Self::DataSizeMismatch { expected, actual } => write!(
+                f,
+                "Texture data has {actual} bytes, but the size and format need {expected}"
+            ),
+            Self::RegionOutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            } => write!(
+                f,
+                "The region {width}x{height} at ({x}, {y}) is outside of the texture"
+            ),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "The operation doesn't support {format:?} textures")
+            }
+            Self::InvalidLayer(layer) => write!(f, "The texture has no layer {layer}"),
+            Self::NoLayers => write!(f, "An array texture needs at least one layer"),
+            Self::InvalidLevel(level) => write!(f, "The texture has no mip level {level}"),
+            Self::PixelTypeMismatch { format, requested } => {
+                write!(
+                    f,
+                    "Pixels of a {format:?} texture can't be read as {requested}"
+                )
+            }
+            Self::InvalidUnpackLayout(layout) => write!(f, "Invalid unpack layout: {layout:?}"),
+            Self::InvalidSampleCount { samples, max } => {
+                write!(f, "Invalid sample count {samples}, the maximum is {max}")
+            }
+            Self::ResolveMismatch {
+                format,
+                width,
+                height,
+            } => write!(
+                f,
+                "Cannot resolve into a {width}x{height} {format:?} texture, the size and format have to match"
+            ),
+        }
+    }
+}
+
+impl error::Error for TextureError {}
+
+/// How the rows of pixel data passed to [`Texture::update_region_with_layout`] are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnpackLayout {
+    /// The length of a row in pixels, `0` if rows are as long as the region.
+    /// Allows uploading a part of a bigger image.
+    pub row_length: i32,
+    /// The alignment of the start of every row in bytes, 1, 2, 4 or 8.
+    pub alignment: i32,
+}
+
+impl UnpackLayout {
+    /// Rows as long as the region, without padding.
+    pub const TIGHT: Self = Self {
+        row_length: 0,
+        alignment: 1,
+    };
+
+    /// The byte offset between rows for a region `width` pixels wide.
+    fn stride(&self, width: i32, bytes_per_pixel: usize) -> usize {
+        let row_length = if self.row_length > 0 {
+            self.row_length
+        } else {
+            width
+        };
+        let alignment = self.alignment as usize;
+
+        (row_length as usize * bytes_per_pixel).div_ceil(alignment) * alignment
+    }
+}
+
+impl Default for UnpackLayout {
+    fn default() -> Self {
+        Self::TIGHT
+    }
+}
+
+/// How many mip levels a texture gets, and where their contents come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mipmaps {
+    /// A full chain generated from the base level.
+    #[default]
+    Generate,
+    /// A full chain left undefined, to fill with [`Texture::update_level`]
+    /// or [`Texture::generate_mipmaps`] later.
+    Allocate,
+    /// Only the base level, for pixel art or render targets that are never minified.
+    None,
+}
+
+pub struct Texture {
+    pub(crate) id: u32,
+    width: i32,
+    height: i32,
+    format: TextureFormat,
+    levels: i32,
+}
 
 impl Texture {
     /// Create a new texture with the given bytes.
     /// Uses the RGBA format internally.
+    ///
+    /// # Panics
+    /// If `data` doesn't hold `width * height` RGBA pixels.
     pub fn new(data: &[u8], width: i32, height: i32) -> Self {
-        let mut id = 0_u32;
+        Self::with_format(Some(data), width, height, TextureFormat::Rgba8)
+            .expect("Invalid texture data")
+    }
 
+    /// Create a texture with the given internal format.
+    ///
+    /// `data` has to be tightly packed rows of [`TextureFormat::pixel_type`] values,
+    /// starting at the bottom row. Without data the contents are undefined,
+    /// which is fine for render targets.
+    ///
+    /// Filterable formats get a full mip chain, generated from `data`.
+    pub fn with_format(
+        data: Option<&[u8]>,
+        width: i32,
+        height: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Self::with_mipmaps(data, width, height, format, Mipmaps::Generate)
+    }
+
+    /// [`Texture::with_format`] with control over the mip chain.
+    pub fn with_mipmaps(
+        data: Option<&[u8]>,
+        width: i32,
+        height: i32,
+        format: TextureFormat,
+        mipmaps: Mipmaps,
+    ) -> Result<Self, TextureError> {
+        if width <= 0 || height <= 0 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+        if let Some(data) = data {
+            check_data_size(data, width, height, format)?;
+        }
+
+        let levels = match mipmaps {
+            Mipmaps::Generate | Mipmaps::Allocate if format.is_filterable() => {
+                mip_levels(width, height)
+            }
+            _ => 1,
+        };
+
+        let texture = Self::allocate(width, height, format, levels);
+        if let Some(data) = data {
+            texture.upload_level(0, width, height, data);
+            if mipmaps == Mipmaps::Generate {
+                texture.generate_mipmaps();
+            }
+        }
+
+        Ok(texture)
+    }
+
+    /// Create a texture from explicit mip levels, starting with the base level.
+    ///
+    /// Every level has half the size of the previous one, rounded down, and is laid out
+    /// as described in [`Texture::with_format`]. The chain doesn't have to go down to 1x1.
+    pub fn from_mip_chain(
+        levels: &[&[u8]],
+        width: i32,
+        height: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        if width <= 0 || height <= 0 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+        if levels.is_empty() || levels.len() as i32 > mip_levels(width, height) {
+            return Err(TextureError::InvalidLevel(levels.len() as i32));
+        }
+        for (level, data) in levels.iter().enumerate() {
+            let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+            check_data_size(data, width, height, format)?;
+        }
+
+        let texture = Self::allocate(width, height, format, levels.len() as i32);
+        for (level, data) in levels.iter().enumerate() {
+            let (width, height) = texture.level_size(level as i32)?;
+            texture.upload_level(level as i32, width, height, data);
+        }
+
+        Ok(texture)
+    }
+
+    /// Allocate `levels` mip levels with undefined contents.
+    fn allocate(width: i32, height: i32, format: TextureFormat, levels: i32) -> Self {
+        let mut id = 0_u32;
         unsafe {
             gl::GenTextures(1, ptr::addr_of_mut!(id));
-            gl::BindTexture(gl::TEXTURE_2D, id);
-            gl::TexImage2D(
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, id);
+
+            if gl::TexStorage2D::is_loaded() {
+                gl::TexStorage2D(gl::TEXTURE_2D, levels, format as u32, width, height);
+            } else {
+                for level in 0..levels {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        level,
+                        format as i32,
+                        (width >> level).max(1),
+                        (height >> level).max(1),
+                        0,
+                        format.pixel_format(),
+                        format.pixel_type(),
+                        ptr::null(),
+                    );
+                }
+                // without immutable storage the chain is only complete up to here
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, levels - 1);
+            }
+        }
+
+        let texture = Self {
+            id,
+            width,
+            height,
+            format,
+            levels,
+        };
+        if levels == 1 {
+            // the default minification filter needs mipmaps, and integer
+            // textures can't be filtered linearly at all
+            texture.set_sampler(&SamplerDesc::nearest().mipmap_filter(None));
+        } else if !format.is_filterable() {
+            texture.set_sampler(&SamplerDesc::nearest());
+        }
+
+        texture
+    }
+
+    /// Upload a whole mip level, `data` has to be checked already.
+    fn upload_level(&self, level: i32, width: i32, height: i32, data: &[u8]) {
+        unsafe {
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, self.id);
+            // rows of `R8` or `RGB8` data aren't necessarily 4 byte aligned
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage2D(
                 gl::TEXTURE_2D,
+                level,
                 0,
-                gl::RGBA as i32,
+                0,
+                width,
+                height,
+                self.format.pixel_format(),
+                self.format.pixel_type(),
+                data.as_ptr() as *const c_void,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+    }
+
+    /// Overwrite a whole mip level, in the format described in [`Texture::with_format`].
+    pub fn update_level(&self, level: i32, data: &[u8]) -> Result<(), TextureError> {
+        let (width, height) = self.level_size(level)?;
+        check_data_size(data, width, height, self.format)?;
+        self.upload_level(level, width, height, data);
+
+        Ok(())
+    }
+
+    /// Regenerate every mip level from the base level, e.g. after rendering into it.
+    ///
+    /// Does nothing for textures with a single level, or formats that can't be filtered.
+    pub fn generate_mipmaps(&self) {
+        if self.levels > 1 && self.format.is_filterable() {
+            unsafe {
+                TextureUnits::bind_for_update(gl::TEXTURE_2D, self.id);
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+        }
+    }
+
+    /// Limit sampling, and [`Texture::generate_mipmaps`], to the levels `base..=max`.
+    ///
+    /// Binds the texture to the active texture unit.
+    pub fn set_level_range(&self, base: i32, max: i32) -> Result<(), TextureError> {
+        if base < 0 || base >= self.levels {
+            return Err(TextureError::InvalidLevel(base));
+        }
+        if max < base || max >= self.levels {
+            return Err(TextureError::InvalidLevel(max));
+        }
+
+        unsafe {
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, self.id);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, base);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, max);
+        }
+
+        Ok(())
+    }
+
+    /// Overwrite a part of the base level with tightly packed pixels,
+    /// in the format described in [`Texture::with_format`].
+    ///
+    /// Mipmaps are not regenerated, see [`Texture::generate_mipmaps`].
+    pub fn update_region(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        data: &[u8],
+    ) -> Result<(), TextureError> {
+        self.update_region_with_layout(x, y, width, height, data, UnpackLayout::TIGHT)
+    }
+
+    /// Check that the region is not empty and lies inside of the base level.
+    pub(crate) fn check_region(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    ) -> Result<(), TextureError> {
+        if x < 0
+            || y < 0
+            || width <= 0
+            || height <= 0
+            || x.checked_add(width).is_none_or(|end| end > self.width)
+            || y.checked_add(height).is_none_or(|end| end > self.height)
+        {
+            return Err(TextureError::RegionOutOfBounds {
+                x,
+                y,
                 width,
                 height,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// [`Texture::update_region`] with padded rows, or rows of a bigger image.
+    pub fn update_region_with_layout(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        data: &[u8],
+        layout: UnpackLayout,
+    ) -> Result<(), TextureError> {
+        self.check_region(x, y, width, height)?;
+        if ![1, 2, 4, 8].contains(&layout.alignment)
+            || (layout.row_length != 0 && layout.row_length < width)
+        {
+            return Err(TextureError::InvalidUnpackLayout(layout));
+        }
+
+        let bytes_per_pixel = self.format.bytes_per_pixel();
+        // the last row doesn't need its padding
+        let expected = layout.stride(width, bytes_per_pixel) * (height as usize - 1)
+            + width as usize * bytes_per_pixel;
+        if data.len() < expected {
+            return Err(TextureError::DataSizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        unsafe {
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, self.id);
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, layout.row_length);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, layout.alignment);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
                 0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
+                x,
+                y,
+                width,
+                height,
+                self.format.pixel_format(),
+                self.format.pixel_type(),
                 data.as_ptr() as *const c_void,
             );
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+
+        Ok(())
+    }
+
+    /// Read back every pixel of a mip level, in the format described in
+    /// [`Texture::with_format`].
+    pub fn read_pixels(&self, level: i32) -> Result<Vec<u8>, TextureError> {
+        self.read_pixels_as::<u8>(level)
+    }
+
+    /// [`Texture::read_pixels`] as the component type of the format, e.g. `f32` for
+    /// [`TextureFormat::Rgba32f`] or `u16` bit patterns for half float formats.
+    pub fn read_pixels_as<T: PixelComponent>(&self, level: i32) -> Result<Vec<T>, TextureError> {
+        // bytes can always be read, the other types have to match the format
+        if size_of::<T>() != 1 && !T::accepts_pixel_type(self.format.pixel_type()) {
+            return Err(TextureError::PixelTypeMismatch {
+                format: self.format,
+                requested: std::any::type_name::<T>(),
+            });
+        }
+
+        let (width, height) = self.level_size(level)?;
+        let bytes = width as usize * height as usize * self.format.bytes_per_pixel();
+        let mut pixels = vec![T::default(); bytes / size_of::<T>()];
+
+        unsafe {
+            TextureUnits::bind_for_update(gl::TEXTURE_2D, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTexImage(
+                gl::TEXTURE_2D,
+                level,
+                self.format.pixel_format(),
+                self.format.pixel_type(),
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+        }
 
-            gl::GenerateMipmap(gl::TEXTURE_2D);
+        Ok(pixels)
+    }
+
+    /// The size of a mip level.
+    pub fn level_size(&self, level: i32) -> Result<(i32, i32), TextureError> {
+        if level < 0 || level >= self.levels {
+            return Err(TextureError::InvalidLevel(level));
         }
 
-        Self(id)
+        Ok(((self.width >> level).max(1), (self.height >> level).max(1)))
     }
 
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// The number of mip levels, including the base level.
+    pub fn levels(&self) -> i32 {
+        self.levels
+    }
+
+    #[deprecated(note = "use `Texture::set_sampler(&SamplerDesc::nearest())`")]
     pub fn use_gl_nearest() {
         unsafe {
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
@@ -40,44 +517,109 @@ impl Texture {
             );
         }
     }
+}
 
-    /// Bind a texture to an index
-    fn bind(&self, index: u32) {
-        assert!(
-            index < 32,
-            "The texture index goes outside of the maximum texture range"
-        );
+/// A GL texture object of any target, bindable through [`ActiveTexture`].
+pub trait TextureObject: Debug {
+    /// The GL name of the texture.
+    fn raw_id(&self) -> u32;
 
-        unsafe {
-            gl::ActiveTexture(gl::TEXTURE0 + index);
-            gl::BindTexture(gl::TEXTURE_2D, self.0)
+    /// The target the texture is bound to, e.g. `gl::TEXTURE_2D`.
+    fn target(&self) -> u32;
+}
+
+impl TextureObject for Texture {
+    fn raw_id(&self) -> u32 {
+        self.id
+    }
+
+    fn target(&self) -> u32 {
+        gl::TEXTURE_2D
+    }
+}
+
+/// A type pixel data can be read back as, see [`Texture::read_pixels_as`].
+pub trait PixelComponent: Copy + Default + private::Sealed {
+    /// Whether pixels of this GL type, e.g. `gl::FLOAT`, are made of `Self`.
+    fn accepts_pixel_type(pixel_type: u32) -> bool;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! pixel_component {
+    ($($ty:ty => $($gl:ident)|+),* $(,)?) => {$(
+        impl private::Sealed for $ty {}
+
+        impl PixelComponent for $ty {
+            fn accepts_pixel_type(pixel_type: u32) -> bool {
+                matches!(pixel_type, $(gl::$gl)|+)
+            }
         }
+    )*};
+}
+
+pixel_component! {
+    u8 => UNSIGNED_BYTE,
+    u16 => UNSIGNED_SHORT | HALF_FLOAT,
+    u32 => UNSIGNED_INT | UNSIGNED_INT_24_8 | FLOAT_32_UNSIGNED_INT_24_8_REV,
+    i32 => INT,
+    f32 => FLOAT,
+}
+
+/// Check that `data` holds exactly one tightly packed image of this size.
+fn check_data_size(
+    data: &[u8],
+    width: i32,
+    height: i32,
+    format: TextureFormat,
+) -> Result<(), TextureError> {
+    let expected = width as usize * height as usize * format.bytes_per_pixel();
+    if data.len() != expected {
+        return Err(TextureError::DataSizeMismatch {
+            expected,
+            actual: data.len(),
+        });
     }
+
+    Ok(())
+}
+
+/// The length of a full mip chain for a texture of this size.
+pub(crate) fn mip_levels(width: i32, height: i32) -> i32 {
+    32 - (width.max(height).max(1) as u32).leading_zeros() as i32
 }
 
 impl Drop for Texture {
     fn drop(&mut self) {
-        unsafe { gl::DeleteTextures(1, ptr::addr_of!(self.0)) }
+        TextureUnits::forget_texture(self.id);
+        unsafe { gl::DeleteTextures(1, ptr::addr_of!(self.id)) }
     }
 }
 
 impl Debug for Texture {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Texture").field("id", &self.0).finish()
+        f.debug_struct("Texture")
+            .field("id", &self.id)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
+            .finish()
     }
 }
 
 #[derive(Clone)]
 enum BoundTexture<'a> {
-    Bound(&'a Texture),
+    Bound(&'a dyn TextureObject),
     NotBound,
 }
 
 impl BoundTexture<'_> {
-    pub const fn unwrap(&self) -> &Texture {
+    const fn get(&self) -> Result<&dyn TextureObject, TextureUnitError> {
         match self {
-            Self::Bound(t) => t,
-            _ => panic!("No texture present!"),
+            Self::Bound(t) => Ok(*t),
+            Self::NotBound => Err(TextureUnitError::NoTexture),
         }
     }
 }
@@ -85,7 +627,7 @@ impl BoundTexture<'_> {
 impl Debug for BoundTexture<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let debug: &dyn Debug = match self {
-            Self::Bound(t) => *t,
+            Self::Bound(t) => t,
             Self::NotBound => &"<not bound>",
         };
 
@@ -97,30 +639,80 @@ impl Debug for BoundTexture<'_> {
 
 #[derive(Clone, Debug)]
 pub struct ActiveTexture<'a> {
-    id: u32,
+    /// `None` to take whichever unit is free when the uniform is set.
+    unit: Option<u32>,
     tex: BoundTexture<'a>,
+    sampler: Option<&'a Sampler>,
 }
 
 impl<'a> ActiveTexture<'a> {
-    pub fn new(index: u32) -> Self {
-        assert!(
-            index < 32,
-            "The texture index goes outside of the maximum texture range"
-        );
+    /// Always bind to texture unit `index`.
+    pub fn new(index: u32) -> Result<Self, TextureUnitError> {
+        TextureUnits::check(index)?;
+        Ok(Self {
+            unit: Some(index),
+            tex: BoundTexture::NotBound,
+            sampler: None,
+        })
+    }
+
+    /// Bind to a unit no other texture of the draw uses, picked when the uniform is set.
+    pub fn auto() -> Self {
         Self {
-            id: index,
+            unit: None,
             tex: BoundTexture::NotBound,
+            sampler: None,
         }
     }
 
-    pub fn bind_texture(&mut self, texture: &'a Texture) {
+    pub fn bind_texture(&mut self, texture: &'a impl TextureObject) {
         self.tex = BoundTexture::Bound(texture);
     }
+
+    /// Sample through `sampler` instead of the state set with [`Texture::set_sampler`].
+    pub fn with_sampler(mut self, sampler: &'a Sampler) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    pub fn set_sampler(&mut self, sampler: Option<&'a Sampler>) {
+        self.sampler = sampler;
+    }
 }
 
 impl Uniform for ActiveTexture<'_> {
-    unsafe fn put_uniform(&self, location: i32) {
-        self.tex.unwrap().bind(self.id);
-        unsafe { gl::Uniform1i(location, self.id as i32) }
+    unsafe fn put_uniform(&self, location: i32) -> Result<(), ShaderError> {
+        let texture = self.tex.get().map_err(ShaderError::TextureUnit)?;
+        let (target, id) = (texture.target(), texture.raw_id());
+        let unit = match self.unit {
+            Some(unit) => TextureUnits::bind_fixed(unit, target, id).map(|()| unit),
+            None => TextureUnits::bind_any(target, id),
+        }
+        .map_err(ShaderError::TextureUnit)?;
+        // unbinding matters too, a sampler left on the unit would override the texture
+        TextureUnits::bind_sampler(unit, self.sampler.map_or(0, |s| s.id));
+        unsafe { gl::Uniform1i(location, unit as i32) };
+        Ok(())
+    }
+
+    fn accepts_gl_type(gl_type: u32) -> bool {
+        matches!(
+            gl_type,
+            gl::SAMPLER_2D
+                | gl::INT_SAMPLER_2D
+                | gl::UNSIGNED_INT_SAMPLER_2D
+                | gl::SAMPLER_CUBE
+                | gl::INT_SAMPLER_CUBE
+                | gl::UNSIGNED_INT_SAMPLER_CUBE
+                | gl::SAMPLER_2D_ARRAY
+                | gl::INT_SAMPLER_2D_ARRAY
+                | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+                | gl::SAMPLER_3D
+                | gl::INT_SAMPLER_3D
+                | gl::UNSIGNED_INT_SAMPLER_3D
+                | gl::SAMPLER_2D_MULTISAMPLE
+                | gl::INT_SAMPLER_2D_MULTISAMPLE
+                | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE
+        )
     }
 }
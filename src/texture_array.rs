@@ -0,0 +1,297 @@
+use crate::{SamplerDesc, TextureError, TextureFormat, TextureObject, TextureUnits, mip_levels};
+use std::{ffi::c_void, fmt::Debug, ptr};
+
+/// Storage shared by array and 3D textures, which only differ in whether
+/// the depth gets smaller with every mip level.
+struct Storage3d {
+    id: u32,
+    target: u32,
+    width: i32,
+    height: i32,
+    depth: i32,
+    format: TextureFormat,
+    levels: i32,
+}
+
+impl Storage3d {
+    fn new(
+        target: u32,
+        width: i32,
+        height: i32,
+        depth: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        if width <= 0 || height <= 0 || depth <= 0 {
+            return Err(TextureError::InvalidSize { width, height });
+        }
+
+        let levels = match (format.is_filterable(), target) {
+            (false, _) => 1,
+            (true, gl::TEXTURE_3D) => mip_levels(width.max(depth), height),
+            (true, _) => mip_levels(width, height),
+        };
+
+        let mut id = 0_u32;
+        unsafe {
+            gl::GenTextures(1, ptr::addr_of_mut!(id));
+            TextureUnits::bind_for_update(target, id);
+
+            if gl::TexStorage3D::is_loaded() {
+                gl::TexStorage3D(target, levels, format as u32, width, height, depth);
+            } else {
+                gl::TexImage3D(
+                    target,
+                    0,
+                    format as i32,
+                    width,
+                    height,
+                    depth,
+                    0,
+                    format.pixel_format(),
+                    format.pixel_type(),
+                    ptr::null(),
+                );
+            }
+        }
+
+        let storage = Self {
+            id,
+            target,
+            width,
+            height,
+            depth,
+            format,
+            levels,
+        };
+        if levels == 1 {
+            // the default minification filter needs mipmaps
+            storage.set_sampler(&SamplerDesc::nearest().mipmap_filter(None));
+        }
+
+        Ok(storage)
+    }
+
+    /// Upload `count` layers of the base level starting at `first`.
+    fn upload(&self, first: i32, count: i32, data: &[u8]) -> Result<(), TextureError> {
+        if first < 0 || first.checked_add(count).is_none_or(|end| end > self.depth) {
+            return Err(TextureError::InvalidLayer(first));
+        }
+
+        let expected = self.width as usize
+            * self.height as usize
+            * count as usize
+            * self.format.bytes_per_pixel();
+        if data.len() != expected {
+            return Err(TextureError::DataSizeMismatch {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        unsafe {
+            TextureUnits::bind_for_update(self.target, self.id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage3D(
+                self.target,
+                0,
+                0,
+                0,
+                first,
+                self.width,
+                self.height,
+                count,
+                self.format.pixel_format(),
+                self.format.pixel_type(),
+                data.as_ptr() as *const c_void,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+
+        Ok(())
+    }
+
+    fn generate_mipmaps(&self) {
+        if self.levels > 1 {
+            unsafe {
+                TextureUnits::bind_for_update(self.target, self.id);
+                gl::GenerateMipmap(self.target);
+            }
+        }
+    }
+
+    fn set_sampler(&self, desc: &SamplerDesc) {
+        desc.apply_to_texture(self.target, self.id);
+    }
+}
+
+impl Drop for Storage3d {
+    fn drop(&mut self) {
+        TextureUnits::forget_texture(self.id);
+        unsafe { gl::DeleteTextures(1, ptr::addr_of!(self.id)) }
+    }
+}
+
+/// Layers of same sized images, sampled with `vec3(uv, layer)` through a `sampler2DArray`.
+///
+/// Unlike an atlas, filtering never bleeds between layers.
+pub struct Texture2DArray(Storage3d);
+
+impl Texture2DArray {
+    /// Create an array with undefined contents, fill it with [`Texture2DArray::upload_layer`].
+    pub fn new(
+        width: i32,
+        height: i32,
+        layers: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        Storage3d::new(gl::TEXTURE_2D_ARRAY, width, height, layers, format).map(Self)
+    }
+
+    /// Create an array with one layer per element of `layers` and generate its mipmaps.
+    pub fn from_layers(
+        layers: &[&[u8]],
+        width: i32,
+        height: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        if layers.is_empty() {
+            return Err(TextureError::NoLayers);
+        }
+
+        let array = Self::new(width, height, layers.len() as i32, format)?;
+        for (index, layer) in layers.iter().enumerate() {
+            array.upload_layer(index as i32, layer)?;
+        }
+        array.generate_mipmaps();
+
+        Ok(array)
+    }
+
+    /// Overwrite the base level of a layer, in the format described in
+    /// [`Texture::with_format`](crate::Texture::with_format).
+    ///
+    /// Mipmaps are not regenerated.
+    pub fn upload_layer(&self, layer: i32, data: &[u8]) -> Result<(), TextureError> {
+        self.0.upload(layer, 1, data)
+    }
+
+    pub fn generate_mipmaps(&self) {
+        self.0.generate_mipmaps();
+    }
+
+    /// Set how the array is sampled. Binds it to the active texture unit.
+    pub fn set_sampler(&self, desc: &SamplerDesc) {
+        self.0.set_sampler(desc);
+    }
+
+    pub fn width(&self) -> i32 {
+        self.0.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.0.height
+    }
+
+    pub fn layers(&self) -> i32 {
+        self.0.depth
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.0.format
+    }
+
+    pub fn levels(&self) -> i32 {
+        self.0.levels
+    }
+}
+
+/// A volume texture, sampled with `vec3` coordinates through a `sampler3D`.
+pub struct Texture3D(Storage3d);
+
+impl Texture3D {
+    /// Create a volume texture from slices stored one after another, or with
+    /// undefined contents if `data` is `None`.
+    pub fn new(
+        data: Option<&[u8]>,
+        width: i32,
+        height: i32,
+        depth: i32,
+        format: TextureFormat,
+    ) -> Result<Self, TextureError> {
+        let texture = Self(Storage3d::new(
+            gl::TEXTURE_3D,
+            width,
+            height,
+            depth,
+            format,
+        )?);
+        if let Some(data) = data {
+            texture.0.upload(0, depth, data)?;
+            texture.generate_mipmaps();
+        }
+
+        Ok(texture)
+    }
+
+    /// Overwrite one slice of the base level. Mipmaps are not regenerated.
+    pub fn upload_slice(&self, z: i32, data: &[u8]) -> Result<(), TextureError> {
+        self.0.upload(z, 1, data)
+    }
+
+    pub fn generate_mipmaps(&self) {
+        self.0.generate_mipmaps();
+    }
+
+    /// Set how the texture is sampled. Binds it to the active texture unit.
+    pub fn set_sampler(&self, desc: &SamplerDesc) {
+        self.0.set_sampler(desc);
+    }
+
+    pub fn width(&self) -> i32 {
+        self.0.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.0.height
+    }
+
+    pub fn depth(&self) -> i32 {
+        self.0.depth
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.0.format
+    }
+
+    pub fn levels(&self) -> i32 {
+        self.0.levels
+    }
+}
+
+macro_rules! texture_object {
+    ($($ty:ident),*) => {$(
+        impl TextureObject for $ty {
+            fn raw_id(&self) -> u32 {
+                self.0.id
+            }
+
+            fn target(&self) -> u32 {
+                self.0.target
+            }
+        }
+
+        impl Debug for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct(stringify!($ty))
+                    .field("id", &self.0.id)
+                    .field("width", &self.0.width)
+                    .field("height", &self.0.height)
+                    .field("depth", &self.0.depth)
+                    .field("format", &self.0.format)
+                    .finish()
+            }
+        }
+    )*};
+}
+
+texture_object!(Texture2DArray, Texture3D);
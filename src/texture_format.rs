@@ -0,0 +1,110 @@
+/// The internal format of a texture.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    R8 = gl::R8,
+    Rg8 = gl::RG8,
+    Rgb8 = gl::RGB8,
+    Rgba8 = gl::RGBA8,
+    Srgb8 = gl::SRGB8,
+    Srgb8Alpha8 = gl::SRGB8_ALPHA8,
+    R16f = gl::R16F,
+    Rg16f = gl::RG16F,
+    Rgba16f = gl::RGBA16F,
+    R32f = gl::R32F,
+    Rg32f = gl::RG32F,
+    Rgba32f = gl::RGBA32F,
+    R8ui = gl::R8UI,
+    R32ui = gl::R32UI,
+    Rgba32ui = gl::RGBA32UI,
+    R32i = gl::R32I,
+    Depth16 = gl::DEPTH_COMPONENT16,
+    Depth24 = gl::DEPTH_COMPONENT24,
+    Depth32f = gl::DEPTH_COMPONENT32F,
+    Depth24Stencil8 = gl::DEPTH24_STENCIL8,
+    Depth32fStencil8 = gl::DEPTH32F_STENCIL8,
+}
+
+/// What kind of values a [`TextureFormat`] stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    /// Normalized or floating point color, can be filtered.
+    Float,
+    SignedInteger,
+    UnsignedInteger,
+    Depth,
+    DepthStencil,
+}
+
+impl TextureFormat {
+    /// The format of the pixel data passed to and read from GL, e.g. `gl::RGBA`.
+    pub const fn pixel_format(self) -> u32 {
+        match self {
+            Self::R8 | Self::R16f | Self::R32f => gl::RED,
+            Self::Rg8 | Self::Rg16f | Self::Rg32f => gl::RG,
+            Self::Rgb8 | Self::Srgb8 => gl::RGB,
+            Self::Rgba8 | Self::Srgb8Alpha8 | Self::Rgba16f | Self::Rgba32f => gl::RGBA,
+            Self::R8ui | Self::R32ui | Self::R32i => gl::RED_INTEGER,
+            Self::Rgba32ui => gl::RGBA_INTEGER,
+            Self::Depth16 | Self::Depth24 | Self::Depth32f => gl::DEPTH_COMPONENT,
+            Self::Depth24Stencil8 | Self::Depth32fStencil8 => gl::DEPTH_STENCIL,
+        }
+    }
+
+    /// The type of the pixel data passed to and read from GL, e.g. `gl::UNSIGNED_BYTE`.
+    /// Half float formats take `f16` bit patterns.
+    pub const fn pixel_type(self) -> u32 {
+        match self {
+            Self::R8 | Self::Rg8 | Self::Rgb8 | Self::Rgba8 | Self::Srgb8 | Self::Srgb8Alpha8 => {
+                gl::UNSIGNED_BYTE
+            }
+            Self::R8ui => gl::UNSIGNED_BYTE,
+            Self::R16f | Self::Rg16f | Self::Rgba16f => gl::HALF_FLOAT,
+            Self::R32f | Self::Rg32f | Self::Rgba32f | Self::Depth32f => gl::FLOAT,
+            Self::R32ui | Self::Rgba32ui | Self::Depth24 => gl::UNSIGNED_INT,
+            Self::R32i => gl::INT,
+            Self::Depth16 => gl::UNSIGNED_SHORT,
+            Self::Depth24Stencil8 => gl::UNSIGNED_INT_24_8,
+            Self::Depth32fStencil8 => gl::FLOAT_32_UNSIGNED_INT_24_8_REV,
+        }
+    }
+
+    pub const fn channels(self) -> usize {
+        match self.pixel_format() {
+            gl::RED | gl::RED_INTEGER | gl::DEPTH_COMPONENT => 1,
+            gl::RG | gl::DEPTH_STENCIL => 2,
+            gl::RGB => 3,
+            _ => 4,
+        }
+    }
+
+    /// The size of one pixel in the data passed to GL.
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self.pixel_type() {
+            gl::UNSIGNED_INT_24_8 => 4,
+            gl::FLOAT_32_UNSIGNED_INT_24_8_REV => 8,
+            gl::UNSIGNED_BYTE => self.channels(),
+            gl::HALF_FLOAT | gl::UNSIGNED_SHORT => self.channels() * 2,
+            _ => self.channels() * 4,
+        }
+    }
+
+    pub const fn kind(self) -> FormatKind {
+        match self {
+            Self::R8ui | Self::R32ui | Self::Rgba32ui => FormatKind::UnsignedInteger,
+            Self::R32i => FormatKind::SignedInteger,
+            Self::Depth16 | Self::Depth24 | Self::Depth32f => FormatKind::Depth,
+            Self::Depth24Stencil8 | Self::Depth32fStencil8 => FormatKind::DepthStencil,
+            _ => FormatKind::Float,
+        }
+    }
+
+    /// Whether the format supports linear filtering and mipmap generation.
+    pub const fn is_filterable(self) -> bool {
+        matches!(self.kind(), FormatKind::Float)
+    }
+
+    pub const fn is_srgb(self) -> bool {
+        matches!(self, Self::Srgb8 | Self::Srgb8Alpha8)
+    }
+}
@@ -0,0 +1,324 @@
+use crate::{Mipmaps, Texture, TextureError, TextureFormat};
+use core::{error, fmt};
+use image::{DynamicImage, ImageBuffer, ImageError, ImageReader, RgbaImage};
+use std::{ffi::c_void, io::Cursor, path::Path};
+
+#[derive(Debug)]
+pub enum ImageLoadError {
+    /// The file couldn't be read or decoded.
+    Image(ImageError),
+    /// Only 8 bit and float color formats can be loaded from images.
+    UnsupportedFormat(TextureFormat),
+    Texture(TextureError),
+}
+
+impl fmt::Display for ImageLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Image(e) => write!(f, "Cannot decode the image: {e}"),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "An image can't be loaded into a {format:?} texture")
+            }
+            Self::Texture(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ImageLoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Image(e) => Some(e),
+            Self::UnsupportedFormat(_) => None,
+            Self::Texture(e) => Some(e),
+        }
+    }
+}
+
+impl From<ImageError> for ImageLoadError {
+    fn from(value: ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+impl From<TextureError> for ImageLoadError {
+    fn from(value: TextureError) -> Self {
+        Self::Texture(value)
+    }
+}
+
+/// How [`Texture::from_path`] and friends turn an image into a texture.
+///
+/// ```ignore
+/// let normals = Texture::from_path("normal.png", &ImageLoadOptions::new(TextureFormat::Rgb8))?;
+/// let tiles = Texture::from_path("tiles.png", &ImageLoadOptions::default().mipmaps(Mipmaps::None))?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLoadOptions {
+    /// Channels the format doesn't have are dropped, missing ones are filled like GL does.
+    pub format: TextureFormat,
+    /// Put the top row of the image at the top of the texture, where `v = 1`.
+    pub flip_vertically: bool,
+    pub mipmaps: Mipmaps,
+}
+
+impl ImageLoadOptions {
+    pub const fn new(format: TextureFormat) -> Self {
+        Self {
+            format,
+            flip_vertically: true,
+            mipmaps: Mipmaps::Generate,
+        }
+    }
+
+    pub const fn format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub const fn flip_vertically(mut self, flip: bool) -> Self {
+        self.flip_vertically = flip;
+        self
+    }
+
+    pub const fn mipmaps(mut self, mipmaps: Mipmaps) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+}
+
+impl Default for ImageLoadOptions {
+    /// RGBA8, flipped, with generated mipmaps.
+    fn default() -> Self {
+        Self::new(TextureFormat::Rgba8)
+    }
+}
+
+impl Texture {
+    /// Decode an image file, its format is guessed from the contents.
+    pub fn from_path(
+        path: impl AsRef<Path>,
+        options: &ImageLoadOptions,
+    ) -> Result<Self, ImageLoadError> {
+        let image = ImageReader::open(path)
+            .map_err(ImageError::IoError)?
+            .with_guessed_format()
+            .map_err(ImageError::IoError)?
+            .decode()?;
+        Self::from_image(&image, options)
+    }
+
+    /// Decode an image file that's already in memory, e.g. from `include_bytes!`.
+    pub fn from_memory(bytes: &[u8], options: &ImageLoadOptions) -> Result<Self, ImageLoadError> {
+        let image = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(ImageError::IoError)?
+            .decode()?;
+        Self::from_image(&image, options)
+    }
+
+    /// Upload a decoded image, converting it to the format of `options`.
+    /// Two channel formats take luma and alpha from grayscale images with alpha,
+    /// like [`Texture::to_image`] writes them, and red and green from everything else.
+    pub fn from_image(
+        image: &DynamicImage,
+        options: &ImageLoadOptions,
+    ) -> Result<Self, ImageLoadError> {
+        let format = options.format;
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let luma_alpha = !image.color().has_color() && image.color().has_alpha();
+        let picked: &[usize] = match format.channels() {
+            2 if luma_alpha => &[0, 3],
+            channels => &[0, 1, 2, 3][..channels],
+        };
+
+        let mut pixels: Vec<u8> = match format {
+            TextureFormat::R8
+            | TextureFormat::Rg8
+            | TextureFormat::Rgb8
+            | TextureFormat::Rgba8
+            | TextureFormat::Srgb8
+            | TextureFormat::Srgb8Alpha8 => image
+                .to_rgba8()
+                .chunks_exact(4)
+                .flat_map(|pixel| picked.iter().map(|&c| pixel[c]))
+                .collect(),
+            TextureFormat::R16f | TextureFormat::Rg16f | TextureFormat::Rgba16f => image
+                .to_rgba32f()
+                .chunks_exact(4)
+                .flat_map(|pixel| picked.iter().map(|&c| pixel[c]))
+                .flat_map(|c| f16_bits(c).to_ne_bytes())
+                .collect(),
+            TextureFormat::R32f | TextureFormat::Rg32f | TextureFormat::Rgba32f => image
+                .to_rgba32f()
+                .chunks_exact(4)
+                .flat_map(|pixel| picked.iter().map(|&c| pixel[c]))
+                .flat_map(|c| c.to_ne_bytes())
+                .collect(),
+            format => return Err(ImageLoadError::UnsupportedFormat(format)),
+        };
+
+        if options.flip_vertically {
+            // images start with the top row, GL with the bottom one
+            flip_rows(&mut pixels, width * format.bytes_per_pixel());
+        }
+
+        Ok(Self::with_mipmaps(
+            Some(&pixels),
+            width as i32,
+            height as i32,
+            format,
+            options.mipmaps,
+        )?)
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageExportError {
+    Texture(TextureError),
+    /// Only 8 bit and 32 bit float color formats can be exported.
+    UnsupportedFormat(TextureFormat),
+    Image(ImageError),
+}
+
+impl fmt::Display for ImageExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Texture(e) => e.fmt(f),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "A {format:?} texture can't be exported as an image")
+            }
+            Self::Image(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ImageExportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Texture(e) => Some(e),
+            Self::UnsupportedFormat(_) => None,
+            Self::Image(e) => Some(e),
+        }
+    }
+}
+
+impl From<TextureError> for ImageExportError {
+    fn from(value: TextureError) -> Self {
+        Self::Texture(value)
+    }
+}
+
+impl From<ImageError> for ImageExportError {
+    fn from(value: ImageError) -> Self {
+        Self::Image(value)
+    }
+}
+
+impl Texture {
+    /// Read back a mip level as an image, with the rows flipped so the top row comes first.
+    ///
+    /// Float formats are clamped to `0.0..=1.0` and stored with 8 bits per channel.
+    pub fn to_image(&self, level: i32) -> Result<DynamicImage, ImageExportError> {
+        let (width, height) = self.level_size(level)?;
+        let (width, height) = (width as u32, height as u32);
+
+        let mut pixels = match self.format() {
+            TextureFormat::R32f | TextureFormat::Rg32f | TextureFormat::Rgba32f => self
+                .read_pixels_as::<f32>(level)?
+                .into_iter()
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect(),
+            TextureFormat::R8
+            | TextureFormat::Rg8
+            | TextureFormat::Rgb8
+            | TextureFormat::Rgba8
+            | TextureFormat::Srgb8
+            | TextureFormat::Srgb8Alpha8 => self.read_pixels(level)?,
+            format => return Err(ImageExportError::UnsupportedFormat(format)),
+        };
+        flip_rows(&mut pixels, width as usize * self.format().channels());
+
+        // the buffer size always matches, it comes from the texture
+        let image = match self.format().channels() {
+            1 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8),
+            2 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8),
+            3 => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8),
+            _ => ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8),
+        };
+
+        Ok(image.expect("Pixel buffer should match the texture size"))
+    }
+
+    /// Write a mip level to a PNG file, see [`Texture::to_image`].
+    pub fn save_png(&self, path: impl AsRef<Path>, level: i32) -> Result<(), ImageExportError> {
+        self.to_image(level)?
+            .save_with_format(path, image::ImageFormat::Png)?;
+        Ok(())
+    }
+}
+
+/// Read the viewport of the framebuffer bound for reading, top row first.
+///
+/// Works for windows as well as a [`HeadlessContext`](crate::HeadlessContext),
+/// call it before swapping buffers.
+pub fn capture_framebuffer() -> RgbaImage {
+    let mut viewport = [0; 4];
+    unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+    let [x, y, width, height] = viewport;
+
+    let row_bytes = width as usize * 4;
+    let mut pixels = vec![0_u8; row_bytes * height as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            x,
+            y,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+        );
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+    }
+    flip_rows(&mut pixels, row_bytes);
+
+    RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .expect("Pixel buffer should match the viewport size")
+}
+
+/// Reverse the order of the rows, GL stores the bottom row first.
+pub(crate) fn flip_rows(pixels: &mut [u8], row_bytes: usize) {
+    let rows = pixels.len() / row_bytes;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - row - 1) * row_bytes);
+        top[row * row_bytes..(row + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+    }
+}
+
+/// Convert to a half float, rounding to the nearest value.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32 - 127 + 15;
+    let mantissa = bits & 0x7F_FFFF;
+
+    if value.is_nan() {
+        sign | 0x7E00
+    } else if exponent >= 31 {
+        sign | 0x7C00
+    } else if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        // subnormal, the implicit leading bit becomes explicit
+        let shift = (14 - exponent) as u32;
+        let mantissa = mantissa | 0x80_0000;
+        let rounded = (mantissa + (1 << (shift - 1))) >> shift;
+        sign | rounded as u16
+    } else {
+        // a carry out of the mantissa correctly bumps the exponent
+        let half = ((exponent as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1);
+        sign | half as u16
+    }
+}
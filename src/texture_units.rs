@@ -0,0 +1,304 @@
+use core::{error, fmt};
+use std::{cell::RefCell, ptr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureUnitError {
+    /// The unit is not below `GL_MAX_COMBINED_TEXTURE_IMAGE_UNITS`.
+    OutOfRange { unit: u32, max: u32 },
+    /// Every unit is already used by the current draw.
+    Exhausted { max: u32 },
+    /// Another texture was bound to the unit earlier in the same draw.
+    UnitInUse(u32),
+    /// The [`ActiveTexture`](crate::ActiveTexture) has no texture bound.
+    NoTexture,
+}
+
+impl fmt::Display for TextureUnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange { unit, max } => write!(
+                f,
+                "Texture unit {unit} is out of range, the context has {max} units"
+            ),
+            Self::Exhausted { max } => write!(
+                f,
+                "The draw uses more textures than the {max} units of the context"
+            ),
+            Self::UnitInUse(unit) => write!(
+                f,
+                "Texture unit {unit} is already used by another texture in this draw"
+            ),
+            Self::NoTexture => write!(f, "No texture is bound to the active texture"),
+        }
+    }
+}
+
+impl error::Error for TextureUnitError {}
+
+#[derive(Debug, Clone, Copy)]
+struct Claim {
+    draw: u64,
+    /// The target and name of the texture the unit was claimed for.
+    texture: (u32, u32),
+    /// The uniform set with [`Program::put_uniform`](crate::Program::put_uniform),
+    /// `None` for [`Program::set_uniforms`](crate::Program::set_uniforms).
+    location: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Unit {
+    /// The name of the texture bound to each target, targets missing from here are unknown.
+    textures: Vec<(u32, u32)>,
+    sampler: Option<u32>,
+    claim: Option<Claim>,
+}
+
+impl Unit {
+    fn is_bound(&self, target: u32, id: u32) -> bool {
+        self.textures.contains(&(target, id))
+    }
+
+    fn claimed_in(&self, draw: u64) -> Option<&Claim> {
+        self.claim.as_ref().filter(|claim| claim.draw == draw)
+    }
+}
+
+/// The binding state of every texture unit of the context.
+struct UnitState {
+    units: Vec<Unit>,
+    /// The unit selected with `glActiveTexture`, `None` if unknown.
+    active: Option<u32>,
+    /// Starts at 1, so no unit is claimed in the current draw before it begins.
+    draw: u64,
+    /// The program the current draw sets uniforms of, `0` if unknown.
+    program: u32,
+    /// The uniform being set, recorded in the claims.
+    location: Option<i32>,
+    /// Queried on first use, it takes a scan of the extensions.
+    max_anisotropy: Option<f32>,
+}
+
+impl UnitState {
+    fn query() -> Self {
+        let mut max = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS, ptr::addr_of_mut!(max)) };
+
+        Self {
+            // GL 3.3 guarantees 48, fall back to that if the query failed
+            units: vec![Unit::default(); if max > 0 { max as usize } else { 48 }],
+            active: None,
+            draw: 1,
+            program: 0,
+            location: None,
+            max_anisotropy: None,
+        }
+    }
+
+    fn claim(&mut self, unit: u32, target: u32, id: u32) {
+        self.units[unit as usize].claim = Some(Claim {
+            draw: self.draw,
+            texture: (target, id),
+            location: self.location,
+        });
+    }
+
+    fn select(&mut self, unit: u32) {
+        if self.active != Some(unit) {
+            unsafe { gl::ActiveTexture(gl::TEXTURE0 + unit) };
+            self.active = Some(unit);
+        }
+    }
+}
+
+thread_local! {
+    // a context is current on one thread at a time, so the state lives there
+    static UNITS: RefCell<Option<UnitState>> = const { RefCell::new(None) };
+}
+
+fn with_units<R>(f: impl FnOnce(&mut UnitState) -> R) -> R {
+    UNITS.with_borrow_mut(|units| f(units.get_or_insert_with(UnitState::query)))
+}
+
+/// Hands out texture units and remembers what's bound to them, so binding
+/// a texture that's already in place costs no GL call.
+///
+/// The state is kept per thread. Call [`TextureUnits::invalidate`] after binding
+/// textures with raw GL calls or making another context current.
+pub struct TextureUnits;
+
+impl TextureUnits {
+    /// `GL_MAX_COMBINED_TEXTURE_IMAGE_UNITS` of the current context.
+    pub fn max() -> u32 {
+        with_units(|state| state.units.len() as u32)
+    }
+
+    /// Check that `unit` exists.
+    pub fn check(unit: u32) -> Result<(), TextureUnitError> {
+        let max = Self::max();
+        if unit >= max {
+            return Err(TextureUnitError::OutOfRange { unit, max });
+        }
+
+        Ok(())
+    }
+
+    /// The highest anisotropy of the current context, queried once with `query`.
+    pub(crate) fn max_anisotropy(query: fn() -> f32) -> f32 {
+        with_units(|state| *state.max_anisotropy.get_or_insert_with(query))
+    }
+
+    /// Forget every binding, and query the limits again.
+    pub fn invalidate() {
+        UNITS.with_borrow_mut(|units| *units = None);
+    }
+
+    /// Start a new draw, releasing the units claimed by the previous one.
+    ///
+    /// [`Program::set_uniforms`](crate::Program::set_uniforms) does this on its own, and
+    /// [`Program::put_uniform`](crate::Program::put_uniform) when the program changes.
+    /// Only needed between draws of the same program whose uniforms claim the same fixed
+    /// unit for different textures.
+    pub fn begin_draw() {
+        with_units(|state| {
+            state.draw += 1;
+            state.location = None;
+        });
+    }
+
+    /// Start a new draw for every uniform of `program`.
+    pub(crate) fn begin_program(program: u32) {
+        with_units(|state| {
+            state.draw += 1;
+            state.program = program;
+            state.location = None;
+        });
+    }
+
+    /// Prepare to set the uniform at `location` of `program` on its own.
+    ///
+    /// Switching programs starts a new draw. Setting a uniform again releases the unit it
+    /// claimed before, so it can be set once per frame without running out of units.
+    pub(crate) fn begin_uniform(program: u32, location: i32) {
+        with_units(|state| {
+            if state.program != program {
+                state.draw += 1;
+                state.program = program;
+            }
+
+            let draw = state.draw;
+            for unit in &mut state.units {
+                if unit
+                    .claimed_in(draw)
+                    .is_some_and(|claim| claim.location == Some(location))
+                {
+                    unit.claim = None;
+                }
+            }
+            state.location = Some(location);
+        });
+    }
+
+    /// Claim `unit` for the current draw and bind the texture to it.
+    /// Fails if another texture claimed the unit in the same draw.
+    pub(crate) fn bind_fixed(unit: u32, target: u32, id: u32) -> Result<(), TextureUnitError> {
+        with_units(|state| {
+            let claim = state.units[unit as usize].claimed_in(state.draw);
+            if claim.is_some_and(|claim| claim.texture != (target, id)) {
+                return Err(TextureUnitError::UnitInUse(unit));
+            }
+
+            state.claim(unit, target, id);
+            Self::bind_in(state, unit, target, id);
+            Ok(())
+        })
+    }
+
+    /// Claim a unit nobody uses in the current draw and bind the texture to it.
+    ///
+    /// Prefers a unit the texture is already bound to, then the units from the top,
+    /// which leaves the low ones to textures with a fixed unit.
+    pub(crate) fn bind_any(target: u32, id: u32) -> Result<u32, TextureUnitError> {
+        with_units(|state| {
+            let draw = state.draw;
+            let units = &state.units;
+            let free = |unit: &usize| units[*unit].claimed_in(draw).is_none();
+
+            let unit = (0..units.len())
+                .filter(free)
+                .find(|unit| units[*unit].is_bound(target, id))
+                .or_else(|| (0..units.len()).rev().find(free))
+                .ok_or(TextureUnitError::Exhausted {
+                    max: units.len() as u32,
+                })? as u32;
+
+            state.claim(unit, target, id);
+            Self::bind_in(state, unit, target, id);
+            Ok(unit)
+        })
+    }
+
+    /// Bind a sampler to a unit, `0` to sample with the state of the texture.
+    pub(crate) fn bind_sampler(unit: u32, sampler: u32) {
+        with_units(|state| {
+            let slot = &mut state.units[unit as usize].sampler;
+            if *slot != Some(sampler) {
+                unsafe { gl::BindSampler(unit, sampler) };
+                *slot = Some(sampler);
+            }
+        });
+    }
+
+    /// Bind the texture to change its storage or parameters.
+    ///
+    /// Uses the active unit unless a texture of the same target claimed it in the current
+    /// draw, replacing that one would change what the draw samples.
+    pub(crate) fn bind_for_update(target: u32, id: u32) {
+        with_units(|state| {
+            let draw = state.draw;
+            let units = &state.units;
+            let usable = |unit: &u32| {
+                units[*unit as usize]
+                    .claimed_in(draw)
+                    .is_none_or(|claim| claim.texture.0 != target || claim.texture.1 == id)
+            };
+
+            let active = state.active.unwrap_or(0);
+            let unit = Some(active)
+                .filter(usable)
+                .or_else(|| (0..units.len() as u32).find(usable))
+                .unwrap_or(active);
+            Self::bind_in(state, unit, target, id);
+        });
+    }
+
+    /// Forget a deleted texture, GL unbinds it from every unit.
+    pub(crate) fn forget_texture(id: u32) {
+        UNITS.with_borrow_mut(|units| {
+            for unit in units.iter_mut().flat_map(|state| &mut state.units) {
+                unit.textures.retain(|&(_, bound)| bound != id);
+            }
+        });
+    }
+
+    /// Forget a deleted sampler, GL unbinds it from every unit.
+    pub(crate) fn forget_sampler(id: u32) {
+        UNITS.with_borrow_mut(|units| {
+            for unit in units.iter_mut().flat_map(|state| &mut state.units) {
+                if unit.sampler == Some(id) {
+                    unit.sampler = Some(0);
+                }
+            }
+        });
+    }
+
+    fn bind_in(state: &mut UnitState, unit: u32, target: u32, id: u32) {
+        if !state.units[unit as usize].is_bound(target, id) {
+            state.select(unit);
+            unsafe { gl::BindTexture(target, id) };
+
+            let textures = &mut state.units[unit as usize].textures;
+            textures.retain(|&(bound, _)| bound != target);
+            textures.push((target, id));
+        }
+    }
+}
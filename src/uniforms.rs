@@ -0,0 +1,86 @@
+use crate::{Program, ShaderError, TextureUnits};
+use std::{any, rc::Rc};
+
+/// [`Uniforms::layout_key`] and the type name, which tells instantiations of a generic type apart.
+pub(crate) type LayoutKey = (usize, &'static str);
+
+/// A group of uniforms uploaded together with [`Program::set_uniforms`].
+///
+/// Usually derived, fields are mapped to uniforms of the same name:
+///
+/// ```ignore
+/// #[derive(Uniforms)]
+/// struct Globals {
+///     #[uniform(rename = "mvp")]
+///     transform: Mat4,
+///     time: f32,
+/// }
+/// ```
+pub trait Uniforms {
+    /// The uniform names, in the order [`Uniforms::put_uniforms`] expects their locations.
+    const NAMES: &'static [&'static str];
+
+    /// Tells the type apart from the other [`Uniforms`] types in the uniform layouts
+    /// cached by a program. Derived as the address of a static declared for the type.
+    fn layout_key() -> usize;
+
+    /// Whether the uniform at `index` in [`Uniforms::NAMES`] accepts `gl_type`.
+    fn accepts_gl_type(index: usize, gl_type: u32) -> bool;
+
+    /// # Safety
+    /// `locations` must hold the location of every name in [`Uniforms::NAMES`], in order,
+    /// in the program that is currently in use.
+    unsafe fn put_uniforms(&self, locations: &[i32]) -> Result<(), ShaderError>;
+}
+
+impl Program {
+    /// Upload every uniform in `uniforms`.
+    ///
+    /// The first call for a type checks it against the active uniforms of the program,
+    /// later calls reuse the looked up locations.
+    pub fn set_uniforms<U: Uniforms>(&self, uniforms: &U) -> Result<(), ShaderError> {
+        let locations = self.uniform_layout::<U>()?;
+
+        self.use_internal();
+        TextureUnits::begin_program(self.id);
+        unsafe { uniforms.put_uniforms(&locations) }
+    }
+
+    fn uniform_layout<U: Uniforms>(&self) -> Result<Rc<[i32]>, ShaderError> {
+        // `TypeId` would need `U: 'static`, which rules out structs holding textures
+        let key: LayoutKey = (U::layout_key(), any::type_name::<U>());
+        if let Some(locations) = self.uniform_layouts.borrow().get(&key) {
+            return Ok(Rc::clone(locations));
+        }
+
+        let active = self.uniforms();
+        let locations = U::NAMES
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let uniform = active
+                    .iter()
+                    .find(|u| {
+                        // arrays are reported as `name[0]`
+                        u.name.strip_suffix("[0]").unwrap_or(&u.name) == *name && u.location != -1
+                    })
+                    .ok_or_else(|| ShaderError::UnknownUniformLocation((*name).to_owned()))?;
+
+                if !U::accepts_gl_type(index, uniform.gl_type) {
+                    return Err(ShaderError::UniformTypeMismatch(format!(
+                        "{name} is declared as 0x{:x} in the shader",
+                        uniform.gl_type
+                    )));
+                }
+
+                Ok(uniform.location)
+            })
+            .collect::<Result<Rc<[i32]>, _>>()?;
+
+        self.uniform_layouts
+            .borrow_mut()
+            .insert(key, Rc::clone(&locations));
+
+        Ok(locations)
+    }
+}
@@ -0,0 +1,216 @@
+//! Renders small scenes with a software rasterizer and compares them to `tests/goldens`.
+//!
+//! `cargo test --features golden`, add `UPDATE_GOLDENS=1` after intended changes.
+use gl_tests_god_save_me::*;
+
+const VERTEX_SOURCE: &str = r#"
+#version 330 core
+layout (location = 0) in vec2 position;
+layout (location = 1) in vec3 color;
+out vec3 fragment_color;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    fragment_color = color;
+}
+"#;
+
+const FRAGMENT_SOURCE: &str = r#"
+#version 330 core
+in vec3 fragment_color;
+out vec4 color;
+
+void main() {
+    color = vec4(fragment_color, 1.0);
+}
+"#;
+
+const TRIANGLE_DATA: [f32; 15] = [
+    0.0, 0.5, 1.0, 0.819, 0.729, // top
+    0.5, -0.5, 0.807, 0.490, 0.647, // right
+    -0.5, -0.5, 0.745, 0.898, 0.749, // left
+];
+
+fn draw_triangle() -> Result<(), AnyError> {
+    let vao = Vao::new();
+    vao.bind();
+    let buffer = Buffer::new(DrawTarget::Array);
+    buffer.bind();
+    buffer.data(&TRIANGLE_DATA, DrawUsage::StaticDraw);
+
+    let program = Program::new(Shader::new(VERTEX_SOURCE)?, Shader::new(FRAGMENT_SOURCE)?)?;
+    program.use_internal();
+    setup_attribute(0, 2, 0, 5, AttributeType::f32);
+    setup_attribute(1, 3, 2, 5, AttributeType::f32);
+
+    vao.draw_arrays(buffer::DrawMode::Triangles, 0, 3);
+    Ok(())
+}
+
+#[test]
+fn triangle() {
+    GoldenTest::new("triangle")
+        .render(64, 64, |_| {
+            set_clear_color(0.3, 0.8, 1.0, 1.0);
+            clear(ClearFlags::COLOR);
+            draw_triangle()
+        })
+        .unwrap();
+}
+
+#[test]
+fn sprite() {
+    GoldenTest::new("sprite")
+        .render(96, 64, |_| {
+            set_clear_color(0.2, 0.2, 0.2, 1.0);
+            clear(ClearFlags::COLOR);
+
+            // a 2x2 checker, magnified without filtering
+            let pixels = [
+                255, 0, 0, 255, 0, 255, 0, 255, //
+                0, 0, 255, 255, 255, 255, 255, 255,
+            ];
+            let texture =
+                Texture::with_mipmaps(Some(&pixels), 2, 2, TextureFormat::Rgba8, Mipmaps::None)?;
+            let mut active_texture = ActiveTexture::new(0)?;
+            active_texture.bind_texture(&texture);
+
+            let camera = Camera::new((96, 64));
+            let sprite = Sprite::new(active_texture, (32, 32))?;
+            sprite.render((16.0, 16.0), camera.calculate_projection_ortho(), 1.0);
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn render_to_texture() {
+    GoldenTest::new("render_to_texture")
+        .render(64, 64, |context| {
+            let color = Texture::with_mipmaps(None, 16, 16, TextureFormat::Rgba8, Mipmaps::None)?;
+            let mut framebuffer = Framebuffer::new();
+            framebuffer.attach_color(0, &color)?;
+            framebuffer.check()?;
+            {
+                let _target = framebuffer.bind_scoped();
+                set_clear_color(1.0, 1.0, 1.0, 1.0);
+                clear(ClearFlags::COLOR);
+                draw_triangle()?;
+            }
+
+            context.framebuffer().bind();
+            set_clear_color(0.0, 0.0, 0.0, 1.0);
+            clear(ClearFlags::COLOR);
+
+            let mut active_texture = ActiveTexture::auto();
+            active_texture.bind_texture(&color);
+            let camera = Camera::new((64, 64));
+            let sprite = Sprite::new(active_texture, (64, 64))?;
+            sprite.render((0.0, 0.0), camera.calculate_projection_ortho(), 1.0);
+            Ok(())
+        })
+        .unwrap();
+}
+
+const MRT_FRAGMENT_SOURCE: &str = r#"
+#version 330 core
+in vec3 fragment_color;
+out vec4 color;
+out vec4 inverted;
+
+void main() {
+    color = vec4(fragment_color, 1.0);
+    inverted = vec4(1.0 - fragment_color, 1.0);
+}
+"#;
+
+#[test]
+fn multiple_render_targets() {
+    GoldenTest::new("multiple_render_targets")
+        .render(64, 32, |context| {
+            let color = Texture::with_mipmaps(None, 16, 16, TextureFormat::Rgba8, Mipmaps::None)?;
+            let inverted =
+                Texture::with_mipmaps(None, 16, 16, TextureFormat::Rgba8, Mipmaps::None)?;
+
+            let vao = Vao::new();
+            vao.bind();
+            let buffer = Buffer::new(DrawTarget::Array);
+            buffer.bind();
+            buffer.data(&TRIANGLE_DATA, DrawUsage::StaticDraw);
+            let program = Program::new(
+                Shader::new(VERTEX_SOURCE)?,
+                Shader::new(MRT_FRAGMENT_SOURCE)?,
+            )?;
+
+            // attached the other way around, the outputs are routed by name
+            let mut framebuffer = Framebuffer::new();
+            framebuffer.attach_color(0, &inverted)?;
+            framebuffer.attach_color(1, &color)?;
+            framebuffer.route_outputs(&program, &[("color", 1), ("inverted", 0)])?;
+            framebuffer.check()?;
+            {
+                let _target = framebuffer.bind_scoped();
+                framebuffer.clear_color(0, [0.0, 0.0, 1.0, 1.0])?;
+                framebuffer.clear_color(1, [1.0, 0.0, 0.0, 1.0])?;
+
+                program.use_internal();
+                setup_attribute(0, 2, 0, 5, AttributeType::f32);
+                setup_attribute(1, 3, 2, 5, AttributeType::f32);
+                vao.draw_arrays(buffer::DrawMode::Triangles, 0, 3);
+            }
+
+            context.framebuffer().bind();
+            set_clear_color(0.0, 0.0, 0.0, 1.0);
+            clear(ClearFlags::COLOR);
+
+            let camera = Camera::new((64, 32));
+            for (x, texture) in [(0.0, &color), (32.0, &inverted)] {
+                let mut active_texture = ActiveTexture::auto();
+                active_texture.bind_texture(texture);
+                let sprite = Sprite::new(active_texture, (32, 32))?;
+                sprite.render((x, 0.0), camera.calculate_projection_ortho(), 1.0);
+            }
+            Ok(())
+        })
+        .unwrap();
+}
+
+const SAMPLING_FRAGMENT_SOURCE: &str = r#"
+#version 330 core
+in vec3 fragment_color;
+out vec4 color;
+uniform sampler2D image;
+
+void main() {
+    color = texture(image, fragment_color.xy);
+}
+"#;
+
+#[test]
+fn put_uniform_releases_texture_units() {
+    let _context = HeadlessContext::new(4, 4).unwrap();
+    let texture = Texture::with_mipmaps(None, 1, 1, TextureFormat::Rgba8, Mipmaps::None).unwrap();
+    let other = Texture::with_mipmaps(None, 1, 1, TextureFormat::Rgba8, Mipmaps::None).unwrap();
+    let program = || {
+        Program::new(
+            Shader::new(VERTEX_SOURCE).unwrap(),
+            Shader::new(SAMPLING_FRAGMENT_SOURCE).unwrap(),
+        )
+        .unwrap()
+    };
+    let (first, second) = (program(), program());
+
+    // once per frame, for more frames than there are units
+    for frame in 0..TextureUnits::max() * 2 {
+        let mut active_texture = ActiveTexture::auto();
+        active_texture.bind_texture(if frame % 2 == 0 { &texture } else { &other });
+        first.put_uniform("image", &active_texture).unwrap();
+    }
+
+    // every program samples its own texture from unit 0
+    for (program, texture) in [(&first, &texture), (&second, &other), (&first, &texture)] {
+        let mut active_texture = ActiveTexture::new(0).unwrap();
+        active_texture.bind_texture(texture);
+        program.put_uniform("image", &active_texture).unwrap();
+    }
+}